@@ -0,0 +1,45 @@
+//! Badges for directories that are project roots, e.g. "[git]" for
+//! those that contain ".git". The mapping from the file that marks
+//! the project to the badge can be changed in the `[badges]` table of
+//! the config file; an empty badge turns the marker off.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config;
+
+const DEFAULT_MARKERS: [(&str, &str); 4] = [
+    (".git", "git"),
+    ("Cargo.toml", "cargo"),
+    ("package.json", "npm"),
+    ("pyproject.toml", "python")
+];
+
+/// The markers and their badges, the configured ones after the defaults.
+fn markers() -> &'static Vec<(String, String)> {
+    static MARKERS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    MARKERS.get_or_init(|| {
+        let mut markers: Vec<(String, String)> = DEFAULT_MARKERS.iter()
+            .map(|(marker, badge)| (marker.to_string(), badge.to_string()))
+            .collect();
+        if let Some(table) = config::get().table("badges") {
+            for (marker, badge) in &table.entries {
+                let badge = badge.as_str().unwrap_or_default().to_string();
+                match markers.iter_mut().find(|(m, _)| m == marker) {
+                    Some(existing) => { existing.1 = badge }
+                    None => { markers.push((marker.clone(), badge)) }
+                }
+            }
+        }
+        markers.retain(|(_, badge)| !badge.is_empty());
+        markers
+    })
+}
+
+/// The badges of the directory, in the order of the mapping.
+pub fn badges(dir: &Path) -> Vec<&'static str> {
+    markers().iter()
+        .filter(|(marker, _)| dir.join(marker).symlink_metadata().is_ok())
+        .map(|(_, badge)| badge.as_str())
+        .collect()
+}
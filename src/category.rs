@@ -0,0 +1,166 @@
+//! What kind of content regular files hold, judged by their extension:
+//! code, documents, images and so on. The category decides the icon and
+//! the color of a file, and can be grouped by (`--group-by category`) and
+//! filtered on (`--category code,image`). Extensions can be assigned to
+//! other categories in the `[categories]` table of the config file, e.g.
+//!
+//! ```toml
+//! [categories]
+//! ipynb = "code"
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::entry::{EnrichedEntry, EntryKind};
+use crate::format;
+use crate::messages;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Category {
+    Code,
+    Document,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Config
+}
+
+/// The extensions of each category, in lowercase.
+const EXTENSIONS: [(Category, &[&str]); 7] = [
+    (Category::Code, &["c", "cc", "cpp", "cs", "go", "h", "hpp", "hs", "java", "js", "jsx", "kt", "lua",
+        "ml", "php", "pl", "py", "rb", "rs", "scala", "sh", "sql", "swift", "ts", "tsx", "zsh"]),
+    (Category::Document, &["csv", "doc", "docx", "epub", "md", "odp", "ods", "odt", "pdf", "ppt", "pptx",
+        "rst", "rtf", "tex", "txt", "xls", "xlsx"]),
+    (Category::Image, &["bmp", "gif", "heic", "ico", "jpeg", "jpg", "png", "psd", "svg", "tif", "tiff",
+        "webp", "xcf"]),
+    (Category::Audio, &["aac", "flac", "m4a", "mid", "mp3", "ogg", "opus", "wav", "wma"]),
+    (Category::Video, &["avi", "flv", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "webm", "wmv"]),
+    (Category::Archive, &["7z", "bz2", "deb", "gz", "iso", "jar", "lz4", "rar", "rpm", "tar", "tgz", "xz",
+        "zip", "zst"]),
+    (Category::Config, &["cfg", "conf", "env", "ini", "json", "properties", "toml", "xml", "yaml", "yml"])
+];
+
+impl Category {
+
+    pub const ALL: [Category; 7] = [
+        Category::Code, Category::Document, Category::Image, Category::Audio, Category::Video,
+        Category::Archive, Category::Config
+    ];
+
+    pub fn parse(s: &str) -> Option<Category> {
+        Category::ALL.into_iter().find(|c| c.name() == s)
+    }
+
+    /// The position of the category in `ALL`, which sections and
+    /// summaries follow.
+    pub fn rank(&self) -> usize {
+        *self as usize
+    }
+
+    /// The name used on the command line and in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Code => { "code" }
+            Category::Document => { "document" }
+            Category::Image => { "image" }
+            Category::Audio => { "audio" }
+            Category::Video => { "video" }
+            Category::Archive => { "archive" }
+            Category::Config => { "config" }
+        }
+    }
+
+    /// Human-readable name of the category, in plural.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Category::Code => { "Code" }
+            Category::Document => { "Documents" }
+            Category::Image => { "Images" }
+            Category::Audio => { "Audio" }
+            Category::Video => { "Videos" }
+            Category::Archive => { "Archives" }
+            Category::Config => { "Configuration" }
+        }
+    }
+
+    /// The icon of the files of the category; the narrow ones are padded,
+    /// as the icons of the types are.
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Category::Code => { "\u{1F4DC}\u{FE0E}" }
+            Category::Document => { "\u{1F4C4}\u{FE0E}" }
+            Category::Image => { "\u{1F5BC}\u{FE0E} " }
+            Category::Audio => { "\u{1F3B5}\u{FE0E}" }
+            Category::Video => { "\u{1F39E}\u{FE0E}" }
+            Category::Archive => { "\u{1F4E6}\u{FE0E}" }
+            Category::Config => { "\u{2699}\u{FE0E} " }
+        }
+    }
+}
+
+/// Parses a comma-separated list of categories.
+pub fn parse_categories(spec: &str) -> Result<Vec<Category>, String> {
+    spec.split(',')
+        .map(|word| Category::parse(word.trim()).ok_or_else(|| format!("Unknown category '{word}'")))
+        .collect()
+}
+
+/// The category of each extension, with those of the config file
+/// replacing the defaults; unknown categories are ignored.
+fn table() -> &'static HashMap<String, Category> {
+    static TABLE: OnceLock<HashMap<String, Category>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table: HashMap<String, Category> = EXTENSIONS.iter()
+            .flat_map(|(category, extensions)| extensions.iter().map(|ext| (ext.to_string(), *category)))
+            .collect();
+        if let Some(configured) = config::get().table("categories") {
+            for (ext, category) in &configured.entries {
+                if let Some(category) = category.as_str().and_then(Category::parse) {
+                    table.insert(ext.to_lowercase(), category);
+                }
+            }
+        }
+        table
+    })
+}
+
+/// The category of the entry; only regular files have one.
+pub fn of(e: &EnrichedEntry) -> Option<Category> {
+    if e.entry.kind != EntryKind::Regular {
+        return None;
+    }
+    let ext = e.entry.get_extension()?;
+    table().get(&ext.to_lowercase()).copied()
+}
+
+/// The line of `--summary=categories`: how many entries of each category
+/// there are and how big they are, e.g. "code: 34  images: 120 (1.2G)  other: 8".
+/// Categories without entries are left out, as are sizes of zero.
+pub fn print_summary(out: &mut dyn Write, entries: &[&EnrichedEntry]) -> io::Result<()> {
+    let mut counts = [(0, 0); Category::ALL.len()];
+    let mut others = 0;
+    for e in entries {
+        match of(e) {
+            Some(c) => {
+                counts[c.rank()].0 += 1;
+                counts[c.rank()].1 += e.size();
+            }
+            None => { others += 1 }
+        }
+    }
+    let mut parts: Vec<String> = Category::ALL.iter().zip(counts)
+        .filter(|(_, (count, _))| *count > 0)
+        .map(|(c, (count, bytes))| {
+            let label = messages::tr(c.title()).to_lowercase();
+            if bytes > 0 { format!("{label}: {count} ({})", format::human_size(bytes)) } else { format!("{label}: {count}") }
+        })
+        .collect();
+    if others > 0 {
+        parts.push(format!("{}: {others}", messages::tr("other")));
+    }
+    writeln!(out, "{}", parts.join("  "))
+}
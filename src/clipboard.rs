@@ -0,0 +1,51 @@
+//! Copying the paths of the listed entries to the clipboard (`--copy`),
+//! with the OSC 52 escape sequence, which terminals (and terminals over
+//! ssh) hand to the system clipboard.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{self, PathBuf};
+use std::sync::Mutex;
+
+/// The absolute paths of the entries listed so far.
+static PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Remembers the path of a listed entry, made absolute (but with
+/// symlinks kept as they are).
+pub fn add(path: &path::Path) {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    PATHS.lock().unwrap_or_else(|e| e.into_inner()).push(path);
+}
+
+/// The escape sequence that puts the text on the clipboard.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// Puts the paths listed so far on the clipboard, one per line; the
+/// sequence goes to the terminal even when the output is redirected.
+pub fn copy() -> io::Result<()> {
+    let paths = PATHS.lock().unwrap_or_else(|e| e.into_inner());
+    let text: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    let mut tty = fs::OpenOptions::new().write(true).open("/dev/tty")?;
+    tty.write_all(osc52(&text.join("\n")).as_bytes())?;
+    tty.flush()
+}
@@ -0,0 +1,373 @@
+//! Coloring of the names by entry type, with palettes for dark and light
+//! terminal backgrounds.
+
+
+use crate::category::{self, Category};
+use crate::config;
+use crate::environment::Environment;
+use crate::entry::{EnrichedEntry, EntryKind};
+use crate::sys;
+
+/// When to use colors (`--color`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never
+}
+
+impl ColorMode {
+
+    pub fn parse(s: &str) -> Option<ColorMode> {
+        match s {
+            "auto" => { Some(ColorMode::Auto) }
+            "always" => { Some(ColorMode::Always) }
+            "never" => { Some(ColorMode::Never) }
+            _ => { None }
+        }
+    }
+}
+
+/// The background the colors are chosen for (`--theme`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme {
+    Dark,
+    Light,
+
+    /// guess from the COLORFGBG variable, falling back to dark
+    Auto
+}
+
+impl Theme {
+
+    pub fn parse(s: &str) -> Option<Theme> {
+        match s {
+            "dark" => { Some(Theme::Dark) }
+            "light" => { Some(Theme::Light) }
+            "auto" => { Some(Theme::Auto) }
+            _ => { None }
+        }
+    }
+
+    /// Resolves `Auto`. COLORFGBG is set by some terminals (rxvt, konsole)
+    /// to "fg;bg" with the standard color indices; backgrounds 7 and 9-15
+    /// are light.
+    fn resolve(self, env: &dyn Environment) -> Theme {
+        if self != Theme::Auto {
+            return self;
+        }
+        let bg = env.var("COLORFGBG")
+            .and_then(|v| v.rsplit(';').next().and_then(|bg| bg.parse::<u8>().ok()));
+        match bg {
+            Some(7) | Some(9..=15) => { Theme::Light }
+            _ => { Theme::Dark }
+        }
+    }
+}
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorDepth {
+    Basic,
+    Ansi256,
+    TrueColor
+}
+
+impl ColorDepth {
+
+    /// Negotiates the depth from COLORTERM and TERM.
+    fn detect(env: &dyn Environment) -> ColorDepth {
+        let colorterm = env.var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+        if env.var("TERM").unwrap_or_default().contains("256color") {
+            return ColorDepth::Ansi256;
+        }
+        ColorDepth::Basic
+    }
+}
+
+/// What an entry is, as far as its color is concerned.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Directory,
+    Symlink,
+    BrokenSymlink,
+    Pipe,
+    Socket,
+    Device,
+    Executable,
+    Unknown,
+
+    /// directories with the sticky bit (like /tmp) or writable by others,
+    /// which are worth noticing, as in GNU ls
+    StickyDirectory,
+    OtherWritableDirectory,
+    StickyOtherWritableDirectory,
+
+    /// backups and the like, which are dimmed
+    Backup,
+
+    /// the link count of regular files with more than one name, which
+    /// may be aliased by accident
+    MultipleLinks,
+
+    /// owners and groups other than the current ones (`--smart-owner`)
+    Foreign,
+
+    /// regular files by what they hold, e.g. images
+    Category(Category)
+}
+
+impl Role {
+
+    /// The role of the entry, or `None` for plain files, which stay uncolored.
+    pub fn of(e: &EnrichedEntry) -> Option<Role> {
+        let mode = e.metadata.as_ref().map_or(0, |m| m.mode());
+        if e.entry.is_backup() {
+            return Some(Role::Backup);
+        }
+        match e.entry.kind {
+            EntryKind::Directory => {
+                match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+                    (true, true) => { Some(Role::StickyOtherWritableDirectory) }
+                    (true, false) => { Some(Role::StickyDirectory) }
+                    (false, true) => { Some(Role::OtherWritableDirectory) }
+                    (false, false) => { Some(Role::Directory) }
+                }
+            }
+            EntryKind::Symlink { .. } if e.is_broken_symlink() => { Some(Role::BrokenSymlink) }
+            EntryKind::Symlink { .. } => { Some(Role::Symlink) }
+            EntryKind::Pipe => { Some(Role::Pipe) }
+            EntryKind::Socket => { Some(Role::Socket) }
+            EntryKind::CharDevice { .. } | EntryKind::BlockDevice { .. } => { Some(Role::Device) }
+            EntryKind::Unknown => { Some(Role::Unknown) }
+            EntryKind::Regular if mode & 0o111 != 0 => { Some(Role::Executable) }
+            EntryKind::Regular => { category::of(e).map(Role::Category) }
+        }
+    }
+
+    /// The name of the role in the `[colors]` table of the config file.
+    fn key(&self) -> &'static str {
+        match self {
+            Role::Directory => { "directory" }
+            Role::Symlink => { "symlink" }
+            Role::BrokenSymlink => { "broken-symlink" }
+            Role::Pipe => { "pipe" }
+            Role::Socket => { "socket" }
+            Role::Device => { "device" }
+            Role::Executable => { "executable" }
+            Role::Unknown => { "unknown" }
+            Role::StickyDirectory => { "sticky-directory" }
+            Role::OtherWritableDirectory => { "other-writable-directory" }
+            Role::StickyOtherWritableDirectory => { "sticky-other-writable-directory" }
+            Role::Backup => { "backup" }
+            Role::MultipleLinks => { "multiple-links" }
+            Role::Foreign => { "foreign-owner" }
+            Role::Category(category) => { category.name() }
+        }
+    }
+}
+
+/// A color given both exactly and as the closest of the 16 basic
+/// colors (those do not map well automatically, as terminals differ).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Style {
+    rgb: (u8, u8, u8),
+
+    /// SGR code of the basic color
+    basic: u8,
+    bold: bool,
+
+    /// the background color and its basic SGR code, if any
+    background: Option<((u8, u8, u8), u8)>
+}
+
+const fn style(r: u8, g: u8, b: u8, basic: u8, bold: bool) -> Style {
+    Style { rgb: (r, g, b), basic, bold, background: None }
+}
+
+const fn on(style: Style, r: u8, g: u8, b: u8, basic: u8) -> Style {
+    Style { background: Some(((r, g, b), basic)), ..style }
+}
+
+fn palette(theme: Theme, role: Role) -> Style {
+    match (theme, role) {
+        (Theme::Light, Role::Directory) => { style(0, 90, 190, 34, true) }
+        (Theme::Light, Role::Symlink) => { style(0, 125, 125, 36, false) }
+        (Theme::Light, Role::BrokenSymlink) => { style(190, 20, 30, 31, false) }
+        (Theme::Light, Role::Pipe) => { style(140, 95, 0, 33, false) }
+        (Theme::Light, Role::Socket) => { style(135, 40, 155, 35, false) }
+        (Theme::Light, Role::Device) => { style(140, 95, 0, 33, true) }
+        (Theme::Light, Role::Executable) => { style(25, 125, 25, 32, true) }
+        (Theme::Light, Role::Unknown) => { style(190, 20, 30, 31, true) }
+        (Theme::Light, Role::Backup) => { style(160, 160, 160, 37, false) }
+        (Theme::Light, Role::MultipleLinks) => { style(175, 50, 0, 31, true) }
+        (Theme::Light, Role::Foreign) => { style(140, 95, 0, 33, true) }
+        (Theme::Light, Role::Category(category)) => { light_category_style(category) }
+
+        // the backgrounds are the same in both themes, as are those of GNU ls
+        (_, Role::StickyDirectory) => { on(style(255, 255, 255, 37, false), 0, 90, 190, 44) }
+        (_, Role::OtherWritableDirectory) => { on(style(0, 70, 160, 34, false), 80, 175, 80, 42) }
+        (_, Role::StickyOtherWritableDirectory) => { on(style(0, 0, 0, 30, false), 80, 175, 80, 42) }
+        (_, Role::Directory) => { style(95, 175, 240, 94, true) }
+        (_, Role::Symlink) => { style(85, 205, 205, 96, false) }
+        (_, Role::BrokenSymlink) => { style(235, 105, 115, 91, false) }
+        (_, Role::Pipe) => { style(230, 195, 120, 33, false) }
+        (_, Role::Socket) => { style(200, 125, 225, 95, false) }
+        (_, Role::Device) => { style(230, 195, 120, 93, true) }
+        (_, Role::Executable) => { style(150, 200, 120, 92, true) }
+        (_, Role::Unknown) => { style(235, 105, 115, 91, true) }
+        (_, Role::Backup) => { style(110, 110, 110, 90, false) }
+        (_, Role::MultipleLinks) => { style(255, 135, 95, 91, true) }
+        (_, Role::Foreign) => { style(255, 215, 95, 93, true) }
+        (_, Role::Category(category)) => { dark_category_style(category) }
+    }
+}
+
+// archives red and media magenta and cyan, as in the default LS_COLORS
+fn dark_category_style(category: Category) -> Style {
+    match category {
+        Category::Code => { style(215, 215, 175, 37, false) }
+        Category::Document => { style(175, 215, 255, 37, false) }
+        Category::Image => { style(215, 135, 215, 95, false) }
+        Category::Audio => { style(95, 215, 215, 96, false) }
+        Category::Video => { style(215, 135, 255, 95, true) }
+        Category::Archive => { style(255, 95, 95, 91, true) }
+        Category::Config => { style(175, 175, 135, 33, false) }
+    }
+}
+
+fn light_category_style(category: Category) -> Style {
+    match category {
+        Category::Code => { style(95, 95, 0, 33, false) }
+        Category::Document => { style(0, 95, 135, 34, false) }
+        Category::Image => { style(135, 0, 135, 35, false) }
+        Category::Audio => { style(0, 110, 110, 36, false) }
+        Category::Video => { style(110, 0, 175, 35, true) }
+        Category::Archive => { style(175, 0, 0, 31, true) }
+        Category::Config => { style(110, 95, 60, 33, false) }
+    }
+}
+
+/// The SGR codes (e.g. "30;42", as in LS_COLORS) given for the role in
+/// the `[colors]` table of the config file, if any.
+fn configured(role: Role) -> Option<&'static str> {
+    config::get().table("colors")?.get(role.key())?.as_str()
+}
+
+/// The nearest color of the 6x6x6 cube of the 256-color palette.
+fn to_ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest = |c: u8| {
+        (0..6).min_by_key(|i| (LEVELS[*i] as i32 - c as i32).abs()).unwrap_or(0) as u8
+    };
+    16 + 36 * nearest(r) + 6 * nearest(g) + nearest(b)
+}
+
+/// Wraps text in the escape sequences for the chosen colors, or leaves
+/// it alone if colors are off.
+pub struct Painter {
+    enabled: bool,
+    theme: Theme,
+    depth: ColorDepth,
+
+    /// the user whose entries are not dimmed, if others' are
+    dim_others: Option<u32>
+}
+
+/// Whether to color by default, as the conventions of other tools have
+/// it: never with NO_COLOR, always with CLICOLOR_FORCE, never with
+/// CLICOLOR=0, and otherwise on terminals.
+fn auto_enabled(to_terminal: bool, env: &dyn Environment) -> bool {
+    if env.var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if env.var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    if env.var("CLICOLOR").as_deref() == Some("0") {
+        return false;
+    }
+    to_terminal
+}
+
+impl Painter {
+
+    /// With `dim_others`, the entries that the current user does not own
+    /// are dimmed. The variables that say which colors to use, if any,
+    /// are read from `env`.
+    pub fn new(mode: ColorMode, theme: Theme, to_terminal: bool, dim_others: bool,
+               env: &dyn Environment) -> Painter {
+        let enabled = match mode {
+            ColorMode::Always => { true }
+            ColorMode::Never => { false }
+            ColorMode::Auto => { auto_enabled(to_terminal, env) }
+        };
+        let dim_others = dim_others.then(sys::effective_uid);
+        Painter { enabled, theme: theme.resolve(env), depth: ColorDepth::detect(env), dim_others }
+    }
+
+    /// The SGR codes of a color, as foreground (38) or background (48).
+    fn color_codes(&self, rgb: (u8, u8, u8), basic: u8, layer: u8) -> String {
+        let (r, g, b) = rgb;
+        match self.depth {
+            ColorDepth::TrueColor => { format!("{layer};2;{r};{g};{b}") }
+            ColorDepth::Ansi256 => { format!("{layer};5;{}", to_ansi256(rgb)) }
+            ColorDepth::Basic => { basic.to_string() }
+        }
+    }
+
+    fn escape(&self, style: Style) -> String {
+        let bold = if style.bold { "1;" } else { "" };
+        let mut codes = format!("{bold}{}", self.color_codes(style.rgb, style.basic, 38));
+        if let Some((rgb, basic)) = style.background {
+            codes.push(';');
+            codes.push_str(&self.color_codes(rgb, basic, 48));
+        }
+        format!("\x1b[{codes}m")
+    }
+
+    /// The escape sequence for the role, as configured or from the theme.
+    fn role_escape(&self, role: Role) -> String {
+        match configured(role) {
+            Some(codes) => { format!("\x1b[{codes}m") }
+            None => { self.escape(palette(self.theme, role)) }
+        }
+    }
+
+    /// Colors text other than the name (e.g. a cell) in the role's colors.
+    pub fn paint_as(&self, role: Role, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        format!("{}{text}\x1b[0m", self.role_escape(role))
+    }
+
+    /// Dims text that is less important than the name next to it.
+    pub fn dim(&self, text: &str) -> String {
+        if self.enabled { format!("\x1b[2m{text}\x1b[0m") } else { text.to_string() }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Colors the text (usually the name) according to the entry's role,
+    /// and dims it if it belongs to someone else and that was asked for.
+    pub fn paint(&self, e: &EnrichedEntry, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        // faint comes after the color, so that it wins over bold
+        let is_others = self.dim_others.is_some_and(|me| e.uid().is_some_and(|uid| uid != me));
+        let dim = if is_others { "\x1b[2m" } else { "" };
+        match Role::of(e) {
+            Some(role) => { format!("{}{dim}{text}\x1b[0m", self.role_escape(role)) }
+            None if is_others => { format!("{dim}{text}\x1b[0m") }
+            None => { text.to_string() }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! The fields shown for each entry: the columns of the long listing,
+//! and the values of the machine-readable outputs (`--fields`).
+
+use std::os::unix::ffi::OsStrExt;
+
+use crate::entry::{dev_major, dev_minor, EnrichedEntry, EntryKind};
+use crate::filesystems;
+use crate::format::{self, SizeUnit, TimeStyle};
+use crate::trash;
+use crate::users;
+
+/// Settings that affect how the cells are rendered.
+pub struct CellFormat {
+
+    /// the current time, to decide which timestamps are recent
+    pub now: i64,
+
+    /// show sizes as "1.5K" rather than in bytes
+    pub human_sizes: bool,
+
+    /// show sizes in bytes with the thousands separated: "1,234,567"
+    pub grouped_sizes: bool,
+
+    /// show sizes in this unit rather than in the one that fits each
+    pub size_unit: SizeUnit,
+
+    /// show times as "2 days ago" rather than as dates
+    pub relative_time: bool,
+
+    /// how to write the times, unless relative
+    pub time_style: TimeStyle,
+
+    /// mark the ids that have no name, e.g. "#1000", when it was looked for
+    pub mark_unnamed_ids: bool,
+
+    /// give names and paths as they are on disk, escaped by `format::escape_name()`
+    pub escape_names: bool
+}
+
+/// The major and minor number, if the entry is a device and they could
+/// be read.
+pub fn device_numbers(e: &EnrichedEntry) -> Option<(u64, u64)> {
+    match &e.entry.kind {
+        EntryKind::CharDevice { dev_id: Some(dev_id) } | EntryKind::BlockDevice { dev_id: Some(dev_id) } => {
+            Some((dev_major(*dev_id), dev_minor(*dev_id)))
+        }
+        _ => { None }
+    }
+}
+
+/// A field of an entry in the machine-readable outputs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Value {
+    Number(u64),
+    Text(String),
+
+    /// the metadata could not be read, or the field does not apply
+    Missing
+}
+
+/// A column of the long listing (and a field of the other outputs).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Column {
+    Permissions,
+    OctalPermissions,
+    Links,
+    Owner,
+    Group,
+    Size,
+
+    /// the space allocated for the entry, in kilobytes, as `ls -s` shows it
+    Blocks,
+    Time,
+    Type,
+    Name,
+    Path,
+    Target,
+    Filesystem,
+
+    /// where a file in the trash was deleted from, and when
+    TrashPath,
+    TrashDate,
+
+    /// 1 if the name or the path had anything escaped (`--escape-names`), else 0
+    Escaped
+}
+
+/// The fields the machine-readable outputs have unless `--fields` is given.
+pub const MACHINE_DEFAULT: [Column; 10] = [
+    Column::Name, Column::Path, Column::Type, Column::Size, Column::Time,
+    Column::Permissions, Column::Owner, Column::Group, Column::Links, Column::Target
+];
+
+impl Column {
+
+    pub fn parse(s: &str) -> Option<Column> {
+        match s {
+            "perms" => { Some(Column::Permissions) }
+            "octal" => { Some(Column::OctalPermissions) }
+            "links" => { Some(Column::Links) }
+            "owner" => { Some(Column::Owner) }
+            "group" => { Some(Column::Group) }
+            "size" => { Some(Column::Size) }
+            "blocks" => { Some(Column::Blocks) }
+            "mtime" => { Some(Column::Time) }
+            "type" => { Some(Column::Type) }
+            "name" => { Some(Column::Name) }
+            "path" => { Some(Column::Path) }
+            "target" => { Some(Column::Target) }
+            "fs" => { Some(Column::Filesystem) }
+            "trash-path" => { Some(Column::TrashPath) }
+            "trash-date" => { Some(Column::TrashDate) }
+            "escaped" => { Some(Column::Escaped) }
+            _ => { None }
+        }
+    }
+
+    /// The name of the field in `--fields`, CSV headers and JSON keys.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Column::Permissions => { "perms" }
+            Column::OctalPermissions => { "octal" }
+            Column::Links => { "links" }
+            Column::Owner => { "owner" }
+            Column::Group => { "group" }
+            Column::Size => { "size" }
+            Column::Blocks => { "blocks" }
+            Column::Time => { "mtime" }
+            Column::Type => { "type" }
+            Column::Name => { "name" }
+            Column::Path => { "path" }
+            Column::Target => { "target" }
+            Column::Filesystem => { "fs" }
+            Column::TrashPath => { "trash-path" }
+            Column::TrashDate => { "trash-date" }
+            Column::Escaped => { "escaped" }
+        }
+    }
+
+    pub fn is_right_aligned(&self) -> bool {
+        matches!(self, Column::Links | Column::Size | Column::Blocks)
+    }
+
+    /// Whether the column is read from the metadata.
+    fn needs_metadata(&self) -> bool {
+        !matches!(self, Column::Type | Column::Name | Column::Path | Column::Target | Column::Filesystem
+            | Column::TrashPath | Column::TrashDate | Column::Escaped)
+    }
+
+    /// Renders the cell of this column for the given entry; entries
+    /// without metadata get question marks, as in ls.
+    pub fn cell(&self, e: &EnrichedEntry, fmt: &CellFormat) -> String {
+        if *self == Column::Filesystem {
+            return filesystems::of(e).map_or_else(|| "?".to_string(), |fs| fs.describe());
+        }
+        if !self.needs_metadata() {
+            return match self.value(e, fmt) {
+                Value::Text(text) => { text }
+                _ => { String::new() }
+            };
+        }
+        let Some(m) = &e.metadata else {
+            return match self {
+                Column::Permissions => { "??????????".to_string() }
+                Column::OctalPermissions => { "????".to_string() }
+                _ => { "?".to_string() }
+            };
+        };
+        match self {
+            Column::Size if device_numbers(e).is_none() && fmt.grouped_sizes => { format::grouped_number(m.size()) }
+            Column::Size if device_numbers(e).is_none() && fmt.size_unit != SizeUnit::Auto => {
+                format::size_in_unit(m.size(), fmt.size_unit)
+            }
+            Column::Size if device_numbers(e).is_none() && fmt.human_sizes => { format::human_size(m.size()) }
+            Column::Blocks if fmt.human_sizes => { format::human_size(m.blocks() * 512) }
+            Column::Owner if fmt.mark_unnamed_ids && users::user_name(m.uid()).is_none() => { format!("#{}", m.uid()) }
+            Column::Group if fmt.mark_unnamed_ids && users::group_name(m.gid()).is_none() => { format!("#{}", m.gid()) }
+            Column::Time => { time_text(m.mtime(), m.mtime_nsec(), fmt) }
+            _ => {
+                match self.value(e, fmt) {
+                    Value::Number(n) => { n.to_string() }
+                    Value::Text(text) => { text }
+                    Value::Missing => { "?".to_string() }
+                }
+            }
+        }
+    }
+
+    /// The value of this field for the machine-readable outputs: sizes
+    /// in bytes, and times that sort as text, or as `--time-style` says.
+    pub fn value(&self, e: &EnrichedEntry, fmt: &CellFormat) -> Value {
+        match self {
+            Column::Type => { return Value::Text(e.entry.type_name().to_string()) }
+            Column::Name if fmt.escape_names => { return Value::Text(format::escape_name(e.raw_name())) }
+            Column::Name => { return Value::Text(e.entry.get_name().to_string()) }
+            Column::Path if fmt.escape_names => { return Value::Text(format::escape_name(e.path.as_os_str().as_bytes())) }
+            Column::Path => { return Value::Text(e.path.to_string_lossy().into_owned()) }
+            Column::Escaped => {
+                let changed = |raw: &[u8]| format::escape_name(raw).as_bytes() != raw;
+                return Value::Number(u64::from(changed(e.raw_name()) || changed(e.path.as_os_str().as_bytes())));
+            }
+            Column::Filesystem => {
+                return filesystems::of(e).map_or(Value::Missing, |fs| Value::Text(fs.name()));
+            }
+            Column::Target => {
+                return match &e.entry.kind {
+                    EntryKind::Symlink { target } => { Value::Text(target.clone()) }
+                    _ => { Value::Missing }
+                };
+            }
+            Column::TrashPath => {
+                return trash::info(e).map_or(Value::Missing, |info| {
+                    Value::Text(info.original_path.to_string_lossy().into_owned())
+                });
+            }
+            Column::TrashDate => {
+                return trash::info(e).and_then(|info| info.deleted)
+                    .map_or(Value::Missing, |date| Value::Text(date.replacen('T', " ", 1)));
+            }
+            _ => { }
+        }
+        let Some(m) = &e.metadata else {
+            return Value::Missing;
+        };
+        match self {
+            Column::Permissions => { Value::Text(format::permission_string(m.mode())) }
+            Column::OctalPermissions => { Value::Text(format::octal_permissions(m.mode())) }
+            Column::Links => { Value::Number(m.nlink()) }
+            Column::Owner => { Value::Text(users::user_name(m.uid()).map_or_else(|| m.uid().to_string(), str::to_string)) }
+            Column::Group => { Value::Text(users::group_name(m.gid()).map_or_else(|| m.gid().to_string(), str::to_string)) }
+            Column::Size => {
+                match device_numbers(e) {
+
+                    // devices show their numbers instead of a size
+                    Some((major, minor)) => { Value::Text(format!("{major}, {minor}")) }
+                    None => { Value::Number(m.size()) }
+                }
+            }
+            Column::Blocks => { Value::Number(m.blocks().div_ceil(2)) }
+            Column::Time => {
+                match fmt.time_style {
+                    TimeStyle::Default => { Value::Text(format::iso_time(m.mtime())) }
+                    TimeStyle::Unix => { u64::try_from(m.mtime()).map_or_else(|_| Value::Text(m.mtime().to_string()), Value::Number) }
+                    TimeStyle::Rfc3339 => { Value::Text(format::rfc3339_time(m.mtime(), m.mtime_nsec())) }
+                }
+            }
+            _ => { Value::Missing }
+        }
+    }
+}
+
+/// How the time column shows the time: relative, or as `--time-style` says.
+pub fn time_text(secs: i64, nsecs: i64, fmt: &CellFormat) -> String {
+    match fmt.time_style {
+        _ if fmt.relative_time => { format::relative_time(secs, fmt.now) }
+        TimeStyle::Default => { format::format_time(secs, fmt.now) }
+        TimeStyle::Unix => { secs.to_string() }
+        TimeStyle::Rfc3339 => { format::rfc3339_time(secs, nsecs) }
+    }
+}
+
+/// Parses a comma-separated list of fields, e.g. "perms,size,name".
+pub fn parse_fields(s: &str) -> Result<Vec<Column>, String> {
+    s.split(',')
+        .map(|f| Column::parse(f.trim()).ok_or_else(|| format!("Unknown field '{f}'")))
+        .collect()
+}
@@ -0,0 +1,234 @@
+//! The configuration file, in (a subset of) TOML: tables, arrays of
+//! tables, and keys with string, integer, boolean or single-line array
+//! values. It is read from $MY_LS_CONFIG, or else from
+//! $XDG_CONFIG_HOME/my_ls/config.toml (~/.config/my_ls/config.toml).
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>)
+}
+
+impl Value {
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => { Some(s) }
+            _ => { None }
+        }
+    }
+}
+
+/// A `[table]` or one `[[table]]` of an array, with its keys in order.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Table {
+    pub name: String,
+
+    /// whether this is an element of an array of tables
+    pub is_array_item: bool,
+
+    pub entries: Vec<(String, Value)>
+}
+
+impl Table {
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+/// The parsed configuration file; the keys before the first table
+/// header belong to a table with an empty name.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Config {
+    tables: Vec<Table>
+}
+
+impl Config {
+
+    /// The table of the given name, if the file has it.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|t| t.name == name && !t.is_array_item)
+    }
+
+    /// The elements of the array of tables of the given name, in order.
+    pub fn array<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Table> + 'a {
+        self.tables.iter().filter(move |t| t.name == name && t.is_array_item)
+    }
+}
+
+/// Reads a quoted string starting at the beginning of `s`; returns it
+/// and the rest of the input.
+fn parse_string(s: &str) -> Result<(String, &str), String> {
+    let quote = s.chars().next().ok_or("Expected a string")?;
+    let mut result = String::new();
+    let mut chars = s[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => { return Ok((result, &s[i + 2..])) }
+
+            // literal strings ('...') have no escapes
+            '\\' if quote == '"' => {
+                let escaped = chars.next().map(|(_, c)| c);
+                match escaped {
+                    Some('n') => { result.push('\n') }
+                    Some('t') => { result.push('\t') }
+                    Some('r') => { result.push('\r') }
+                    Some('"') => { result.push('"') }
+                    Some('\\') => { result.push('\\') }
+                    Some('u') => {
+                        let digits: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                        let c = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                            .ok_or_else(|| format!("Invalid escape '\\u{digits}'"))?;
+                        result.push(c);
+                    }
+                    _ => { return Err("Invalid escape in string".to_string()) }
+                }
+            }
+            c => { result.push(c) }
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+/// Reads a value starting at the beginning of `s`; returns it and the
+/// rest of the input.
+fn parse_value(s: &str) -> Result<(Value, &str), String> {
+    let s = s.trim_start();
+    if s.starts_with('"') || s.starts_with('\'') {
+        let (string, rest) = parse_string(s)?;
+        return Ok((Value::String(string), rest));
+    }
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("Expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+    let end = s.find(|c: char| c == ',' || c == ']' || c == '#' || c.is_whitespace()).unwrap_or(s.len());
+    let word = &s[..end];
+    let value = match word {
+        "true" => { Value::Boolean(true) }
+        "false" => { Value::Boolean(false) }
+        _ => {
+            let number = word.replace('_', "").parse::<i64>()
+                .map_err(|_| format!("Invalid value '{word}'"))?;
+            Value::Integer(number)
+        }
+    };
+    Ok((value, &s[end..]))
+}
+
+/// Reads a key, bare or quoted; returns it and the rest of the input.
+fn parse_key(s: &str) -> Result<(String, &str), String> {
+    let s = s.trim_start();
+    if s.starts_with('"') || s.starts_with('\'') {
+        return parse_string(s);
+    }
+    let end = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')).unwrap_or(s.len());
+    if end == 0 {
+        return Err("Expected a key".to_string());
+    }
+    Ok((s[..end].to_string(), &s[end..]))
+}
+
+/// Checks that only whitespace or a comment is left on the line.
+fn expect_end(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("Unexpected '{rest}'"))
+    }
+}
+
+fn parse_line(line: &str, tables: &mut Vec<Table>) -> Result<(), String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(());
+    }
+    if let Some(header) = line.strip_prefix("[[") {
+        let (name, rest) = parse_key(header)?;
+        let rest = rest.trim_start().strip_prefix("]]").ok_or("Expected ']]'")?;
+        expect_end(rest)?;
+        tables.push(Table { name, is_array_item: true, entries: Vec::new() });
+        return Ok(());
+    }
+    if let Some(header) = line.strip_prefix('[') {
+        let (name, rest) = parse_key(header)?;
+        let rest = rest.trim_start().strip_prefix(']').ok_or("Expected ']'")?;
+        expect_end(rest)?;
+        if tables.iter().any(|t| t.name == name && !t.is_array_item) {
+            return Err(format!("Table '{name}' defined twice"));
+        }
+        tables.push(Table { name, is_array_item: false, entries: Vec::new() });
+        return Ok(());
+    }
+    let (key, rest) = parse_key(line)?;
+    let rest = rest.trim_start().strip_prefix('=').ok_or("Expected '='")?;
+    let (value, rest) = parse_value(rest)?;
+    expect_end(rest)?;
+    let table = tables.last_mut().ok_or("No table")?;
+    if table.get(&key).is_some() {
+        return Err(format!("Key '{key}' defined twice"));
+    }
+    table.entries.push((key, value));
+    Ok(())
+}
+
+/// Parses the text of a configuration file.
+pub fn parse(text: &str) -> Result<Config, String> {
+    let mut tables = vec![Table::default()];
+    for (i, line) in text.lines().enumerate() {
+        parse_line(line, &mut tables).map_err(|err| format!("line {}: {err}", i + 1))?;
+    }
+    Ok(Config { tables })
+}
+
+/// Where the configuration file is looked for.
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("MY_LS_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let dir = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .filter(|d| d.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("my_ls").join("config.toml"))
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Reads the configuration file, if there is one.
+pub fn load() -> Result<(), String> {
+    let mut config = Config::default();
+    if let Some(path) = config_path() {
+        if let Ok(text) = fs::read_to_string(&path) {
+            config = parse(&text).map_err(|err| format!("Config file '{}', {err}", path.display()))?;
+        }
+    }
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// The configuration; empty if there is no file (or it was not loaded).
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -0,0 +1,54 @@
+//! Reading metadata on a worker thread with a deadline, so that a single
+//! hung network filesystem cannot freeze the whole listing.
+
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::sys::Dir;
+
+type Job = Box<dyn FnOnce(&Dir) + Send>;
+
+/// Does the lookups (`lstat`, `readlink`) for the entries of a directory,
+/// relative to it. When one of them hangs, the directory is taken to be
+/// hung: the worker is left behind, and the remaining entries are not
+/// looked up at all, so at most one thread is lost per directory.
+pub struct StatWorker {
+    timeout: Duration,
+    jobs: Sender<Job>,
+    hung: bool
+}
+
+impl StatWorker {
+
+    pub fn new(dir: &Dir, timeout: Duration) -> io::Result<StatWorker> {
+        let dir = dir.try_clone()?;
+        let (jobs, inbox) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in inbox {
+                job(&dir);
+            }
+        });
+        Ok(StatWorker { timeout, jobs, hung: false })
+    }
+
+    /// Runs the lookup on the worker; `None` if it does not finish in
+    /// time, or if an earlier one has not.
+    pub fn run<T: Send + 'static>(&mut self, lookup: impl FnOnce(&Dir) -> T + Send + 'static) -> Option<T> {
+        if self.hung {
+            return None;
+        }
+        let (outbox, reply) = mpsc::channel();
+
+        // the listing has given up on the reply if nobody is listening
+        let job: Job = Box::new(move |dir| { let _ = outbox.send(lookup(dir)); });
+        if self.jobs.send(job).is_ok() {
+            if let Ok(value) = reply.recv_timeout(self.timeout) {
+                return Some(value);
+            }
+        }
+        self.hung = true;
+        None
+    }
+}
@@ -0,0 +1,230 @@
+//! Default options from the config file, which those on the command line
+//! come after (and so win over). The `[defaults]` table applies everywhere;
+//! each `[[profile]]` only on the hosts and in the directories matching its
+//! patterns, e.g.
+//!
+//! ```toml
+//! [defaults]
+//! hidden = "almost-all"
+//! theme = "light"
+//!
+//! [[profile]]
+//! directory = "~/Downloads"
+//! output = "long"
+//! sort = "time"
+//!
+//! [[profile]]
+//! host = "build-*"
+//! args = ["--no-icons", "--group-by", "type"]
+//! ```
+//!
+//! The settings are `sort` and `theme` (as the options of those names),
+//! `hidden` ("none", "almost-all" or "all"), `output` ("long", "grid",
+//! "lines" or a format of `--output`) and `args`, for any other options.
+//! A setting of a later profile replaces that of the earlier ones and of
+//! the defaults.
+//!
+//! A directory can have its own settings, in a `.my_ls.toml` file at its
+//! top (without a table header). The nearest one, in the directory listed
+//! or above, comes after the config file. As anyone can leave such a file
+//! in a directory, only those of the current user or root are read, and
+//! they may only give options that change how the entries are shown, not
+//! which are listed or what else is done; with `--no-local-config` (also
+//! in `args`), none are read at all.
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Config, Table, Value};
+use crate::glob;
+use crate::sys;
+
+/// The settings, in the order their options are given in.
+const SETTINGS: [&str; 5] = ["sort", "hidden", "theme", "output", "args"];
+
+/// The name of the files with the settings of a directory.
+const LOCAL_FILE: &str = ".my_ls.toml";
+
+/// The options those files may give, and whether each takes a value:
+/// those that only change how the entries are shown, not which ones are
+/// listed or what else is done.
+const LOCAL_OPTIONS: [(&str, bool); 47] = [
+    ("sort", true), ("reverse", false), ("group-by", true), ("no-dirs-first", false), ("full-path", false),
+    ("canonical", false), ("relative-to", true), ("follow-chain", false), ("long", false),
+    ("octal-permissions", false), ("gnu", false), ("human-readable", false), ("bytes", false),
+    ("size", false), ("bar", false), ("link-target-metadata", false), ("no-lookup", false),
+    ("anomalies", false), ("time-style", true), ("size-unit", true), ("relative-time", false),
+    ("output", true), ("fields", true), ("escape-names", false), ("color", true), ("colour", true),
+    ("icons", true), ("no-icons", false), ("theme", true), ("align", true), ("width", true), ("fs", false),
+    ("socket-info", false), ("ipc-age", false), ("trash-info", false), ("dir-slash", false),
+    ("max-name-width", true), ("stat-format", true), ("badges", false), ("no-badges", false), ("stale", true),
+    ("show-errors", true), ("all", false), ("almost-all", false), ("dim-others", false),
+    ("smart-owner", false), ("summary", true)
+];
+
+/// The short options those files may give, none of which takes a value.
+const LOCAL_SHORT_OPTIONS: &str = "StXUrlgohsaA1Cxp";
+
+/// Whether the profile is for this host and directory; a profile without
+/// patterns is for all of them. A directory pattern starting with "~" is
+/// taken relative to the home directory.
+fn applies(profile: &Table, host: Option<&str>, dir: &Path, home: Option<&Path>) -> Result<bool, String> {
+    if let Some(pattern) = profile.get("host") {
+        let pattern = pattern.as_str().ok_or("Setting 'host' must be a string")?;
+        if !host.is_some_and(|host| glob::matches(pattern, host)) {
+            return Ok(false);
+        }
+    }
+    if let Some(pattern) = profile.get("directory") {
+        let pattern = pattern.as_str().ok_or("Setting 'directory' must be a string")?;
+        let pattern = match (pattern.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                format!("{}{rest}", home.display())
+            }
+            _ => { pattern.to_string() }
+        };
+        let pattern = if pattern.len() > 1 { pattern.trim_end_matches('/') } else { &pattern };
+        if !glob::matches(pattern, &dir.to_string_lossy()) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The options that give the setting.
+fn setting_args(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    if key == "args" {
+        let not_strings = || "Setting 'args' must be an array of strings".to_string();
+        let Value::Array(items) = value else {
+            return Err(not_strings());
+        };
+        return items.iter().map(|item| item.as_str().map(str::to_string).ok_or_else(not_strings)).collect();
+    }
+    let value = value.as_str().ok_or_else(|| format!("Setting '{key}' must be a string"))?;
+    let arg = match (key, value) {
+        ("sort", _) => { format!("--sort={value}") }
+        ("theme", _) => { format!("--theme={value}") }
+        ("hidden", "none") => { return Ok(Vec::new()) }
+        ("hidden", "almost-all") => { "--almost-all".to_string() }
+        ("hidden", "all") => { "--all".to_string() }
+        ("output", "long") => { "--long".to_string() }
+        ("output", "grid") => { "-C".to_string() }
+        ("output", "lines") => { "-1".to_string() }
+        ("output", _) => { format!("--output={value}") }
+        _ => { return Err(format!("Invalid value '{value}' for '{key}'")) }
+    };
+    Ok(vec![arg])
+}
+
+/// Adds the settings of the table to those so far, replacing any that
+/// were there.
+fn add_settings(settings: &mut Vec<(&str, Vec<String>)>, table: &Table) -> Result<(), String> {
+    for (key, value) in &table.entries {
+        if table.is_array_item && (key == "host" || key == "directory") {
+            continue;
+        }
+        let Some(key) = SETTINGS.into_iter().find(|s| s == key) else {
+            return Err(format!("Unknown setting '{key}'"));
+        };
+        let args = setting_args(key, value)?;
+        settings.retain(|(k, _)| *k != key);
+        settings.push((key, args));
+    }
+    Ok(())
+}
+
+/// The options that give the settings, in the order of `SETTINGS`.
+fn settings_args(mut settings: Vec<(&str, Vec<String>)>) -> Vec<String> {
+    settings.sort_by_key(|(key, _)| SETTINGS.iter().position(|s| s == key));
+    settings.into_iter().flat_map(|(_, args)| args).collect()
+}
+
+/// The default options the config gives for listing the directory (an
+/// absolute path) on this host.
+pub fn args_from(config: &Config, host: Option<&str>, dir: &Path, home: Option<&Path>) -> Result<Vec<String>, String> {
+    let defaults = config.table("defaults").map(|table| ("[defaults]".to_string(), table));
+    let profiles = config.array("profile").enumerate()
+        .map(|(i, table)| (format!("[[profile]] {}", i + 1), table));
+    let mut settings = Vec::new();
+    for (name, table) in defaults.into_iter().chain(profiles) {
+        let in_table = |err: String| format!("{name}: {err}");
+        if applies(table, host, dir, home).map_err(in_table)? {
+            add_settings(&mut settings, table).map_err(in_table)?;
+        }
+    }
+    Ok(settings_args(settings))
+}
+
+/// The options of a local config file, which are the settings before
+/// its first table.
+pub fn local_args_from(config: &Config) -> Result<Vec<String>, String> {
+    let mut settings = Vec::new();
+    if let Some(table) = config.table("") {
+        add_settings(&mut settings, table)?;
+    }
+    let args = settings_args(settings);
+    check_local_args(&args)?;
+    Ok(args)
+}
+
+/// Fails on the first argument a local config file may not give: an
+/// option not in `LOCAL_OPTIONS`, or an operand (which would change what
+/// is listed).
+fn check_local_args(args: &[String]) -> Result<(), String> {
+    let not_allowed = |arg: &str| Err(format!("Option '{arg}' is not allowed here"));
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(long) = arg.strip_prefix("--").filter(|long| !long.is_empty()) {
+            let (name, inline_value) = long.split_once('=').map_or((long, None), |(name, value)| (name, Some(value)));
+            match LOCAL_OPTIONS.iter().find(|(option, _)| *option == name) {
+                Some((_, true)) if inline_value.is_none() => { args.next(); }
+                Some(_) => { }
+                None => { return not_allowed(arg) }
+            }
+        } else if let Some(short) = arg.strip_prefix('-').filter(|short| !short.is_empty() && !short.starts_with('-')) {
+            if let Some(c) = short.chars().find(|c| !LOCAL_SHORT_OPTIONS.contains(*c)) {
+                return not_allowed(&format!("-{c}"));
+            }
+        } else if arg == "--" {
+            return not_allowed(arg);
+        } else {
+            return Err(format!("Operand '{arg}' is not allowed here"));
+        }
+    }
+    Ok(())
+}
+
+/// The path of the directory, absolute and without symlinks if it exists.
+fn absolute(dir: &Path) -> PathBuf {
+    dir.canonicalize().or_else(|_| std::path::absolute(dir)).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// The default options for listing the directory, from the config file.
+pub fn configured_args(dir: &Path) -> Result<Vec<String>, String> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    args_from(config::get(), sys::hostname().as_deref(), &absolute(dir), home.as_deref())
+        .map_err(|err| format!("Config file, {err}"))
+}
+
+/// The nearest local config file of the directory that the current user
+/// (or root) owns.
+fn find_local(dir: &Path) -> Option<PathBuf> {
+    let uid = sys::effective_uid();
+    absolute(dir).ancestors()
+        .map(|d| d.join(LOCAL_FILE))
+        .find(|path| path.symlink_metadata().is_ok_and(|m| m.is_file() && (m.uid() == uid || m.uid() == 0)))
+}
+
+/// The local config file for listing the directory, if it has one, and
+/// the options from it.
+pub fn local_args(dir: &Path) -> Result<Option<(PathBuf, Vec<String>)>, String> {
+    let Some(path) = find_local(dir) else {
+        return Ok(None);
+    };
+    let in_file = |err: String| format!("Local config file '{}', {err}", path.display());
+    let text = fs::read_to_string(&path).map_err(|err| in_file(err.to_string()))?;
+    let args = local_args_from(&config::parse(&text).map_err(in_file)?).map_err(in_file)?;
+    Ok(Some((path, args)))
+}
@@ -0,0 +1,47 @@
+//! What device nodes outside /dev (e.g. in a chroot or the root
+//! filesystem of a container) stand for on the running system, from
+//! /sys/dev/{char,block}/<major>:<minor>.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::entry::{dev_major, dev_minor, EnrichedEntry, EntryKind};
+use crate::messages;
+
+/// The name of the device in /dev: DEVNAME from its uevent file, or else
+/// the name of the directory the /sys/dev link points to.
+fn dev_name(sys_dev: &Path) -> Option<String> {
+    let uevent = fs::read_to_string(sys_dev.join("uevent")).unwrap_or_default();
+    let from_uevent = uevent.lines().find_map(|line| line.strip_prefix("DEVNAME="));
+    if let Some(name) = from_uevent {
+        return Some(name.to_string());
+    }
+    let target = fs::read_link(sys_dev).ok()?;
+    Some(target.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Whether the entry is in /dev itself, where it needs no resolving.
+fn is_in_dev(e: &EnrichedEntry) -> bool {
+    let parent = e.path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::canonicalize(parent).is_ok_and(|dir| dir.starts_with("/dev"))
+}
+
+/// Describes which device of the running system the node outside /dev
+/// corresponds to: "/dev/sda", or "no such device here" if the kernel
+/// knows none with its numbers. `None` for other entries (and whiteouts,
+/// and devices whose numbers could not be read).
+pub fn describe(e: &EnrichedEntry) -> Option<String> {
+    let (kind, dev_id) = match e.entry.kind {
+        EntryKind::CharDevice { dev_id: Some(dev_id) } if dev_id != 0 => { ("char", dev_id) }
+        EntryKind::BlockDevice { dev_id: Some(dev_id) } => { ("block", dev_id) }
+        _ => { return None }
+    };
+    if is_in_dev(e) {
+        return None;
+    }
+    let sys_dev = PathBuf::from(format!("/sys/dev/{kind}/{}:{}", dev_major(dev_id), dev_minor(dev_id)));
+    match dev_name(&sys_dev) {
+        Some(name) => { Some(format!("/dev/{name}")) }
+        None => { Some(messages::tr("no such device here").to_string()) }
+    }
+}
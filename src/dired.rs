@@ -0,0 +1,88 @@
+//! Support for Emacs dired (`--dired`): the byte offsets of all the names
+//! in the output are collected and printed in a trailer at the end.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+#[derive(Default)]
+struct Offsets {
+
+    /// bytes written to the output so far
+    position: u64,
+
+    /// (start, end) of each entry name
+    names: Vec<(u64, u64)>,
+
+    /// (start, end) of each directory name in the headers
+    subdirs: Vec<(u64, u64)>
+}
+
+thread_local! {
+    static OFFSETS: RefCell<Offsets> = RefCell::new(Offsets::default());
+}
+
+/// Passes everything through, counting the bytes on the way.
+pub struct CountingWriter<W: Write> {
+    inner: W
+}
+
+impl<W: Write> CountingWriter<W> {
+
+    pub fn new(inner: W) -> CountingWriter<W> {
+        CountingWriter { inner }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        OFFSETS.with(|o| o.borrow_mut().position += n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn position() -> u64 {
+    OFFSETS.with(|o| o.borrow().position)
+}
+
+/// Writes the name of an entry, remembering where it is in the output.
+pub fn write_name(out: &mut dyn Write, name: &str) -> io::Result<()> {
+    let start = position();
+    write!(out, "{name}")?;
+    OFFSETS.with(|o| o.borrow_mut().names.push((start, start + name.len() as u64)));
+    Ok(())
+}
+
+/// Writes the name of a directory in its header, remembering where it is.
+pub fn write_subdir(out: &mut dyn Write, name: &str) -> io::Result<()> {
+    let start = position();
+    write!(out, "{name}")?;
+    OFFSETS.with(|o| o.borrow_mut().subdirs.push((start, start + name.len() as u64)));
+    Ok(())
+}
+
+fn write_offsets(out: &mut dyn Write, label: &str, offsets: &[(u64, u64)]) -> io::Result<()> {
+    write!(out, "//{label}//")?;
+    for (start, end) in offsets {
+        write!(out, " {start} {end}")?;
+    }
+    writeln!(out)
+}
+
+/// Prints the trailer dired reads the offsets from.
+pub fn print_trailer(out: &mut dyn Write) -> io::Result<()> {
+    let (names, subdirs) = OFFSETS.with(|o| {
+        let o = o.borrow();
+        (o.names.clone(), o.subdirs.clone())
+    });
+    write_offsets(out, "DIRED", &names)?;
+    if !subdirs.is_empty() {
+        write_offsets(out, "SUBDIRED", &subdirs)?;
+    }
+    writeln!(out, "//DIRED-OPTIONS// --quoting-style=literal")
+}
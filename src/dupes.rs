@@ -0,0 +1,86 @@
+//! The `--dupes` report: groups of files with identical content.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::entry::EntryKind;
+use crate::format;
+use crate::hash;
+use crate::messages;
+use crate::options::{Operand, Options};
+use crate::walk;
+
+/// Splits files with the same hash into groups of truly identical content,
+/// in case two different files happen to share the hash.
+fn confirm(paths: Vec<PathBuf>, open: &hash::Open<'_>) -> Vec<Vec<PathBuf>> {
+    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    for path in paths {
+        match groups.iter_mut().find(|g| hash::same_content(&g[0], &path, open).unwrap_or(false)) {
+            Some(group) => { group.push(path) }
+            None => { groups.push(vec![path]) }
+        }
+    }
+    groups
+}
+
+pub fn print_dupes(out: &mut dyn Write, operand: &Operand, options: &Options) -> io::Result<()> {
+    let root = Path::new(&operand.path);
+
+    // only files of the same size can be duplicates; empty files are
+    // left out, as they are trivially all the same
+    let mut by_size = HashMap::<u64, Vec<PathBuf>>::new();
+    walk::walk_opened(root, operand.open(), options.recursive, &options.filters, &mut |e| {
+        if e.entry.kind == EntryKind::Regular && e.size() > 0 {
+            by_size.entry(e.size()).or_default().push(e.path.clone());
+        }
+    });
+    let candidates: Vec<(u64, PathBuf)> = by_size.into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |p| (size, p)))
+        .collect();
+
+    let paths: Vec<PathBuf> = candidates.iter().map(|(_, p)| p.clone()).collect();
+
+    // the files are opened relative to the directory listed, which may
+    // have been given as a descriptor
+    let dir = operand.open();
+    let open = |path: &Path| match &dir {
+        Ok(dir) => { dir.open_file_at(path.strip_prefix(root).unwrap_or(path)) }
+        Err(err) => { Err(io::Error::from(err.kind())) }
+    };
+    let hashes = hash::hash_files(&paths, &open);
+
+    let mut by_hash = HashMap::<(u64, u64), Vec<PathBuf>>::new();
+    for ((size, path), hash) in candidates.into_iter().zip(hashes) {
+        if let Some(hash) = hash {
+            by_hash.entry((size, hash)).or_default().push(path);
+        }
+    }
+
+    // biggest duplicates first, files within a group sorted by path
+    let mut groups: Vec<(u64, Vec<PathBuf>)> = Vec::new();
+    for ((size, _), paths) in by_hash {
+        if paths.len() < 2 { continue; }
+        for mut group in confirm(paths, &open) {
+            if group.len() > 1 {
+                group.sort();
+                groups.push((size, group));
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    if options.paths.len() > 1 {
+        writeln!(out, "{}:", root.display())?;
+    }
+    for (i, (size, group)) in groups.iter().enumerate() {
+        if i > 0 { writeln!(out)?; }
+        let files = messages::count("{} file|{} files", group.len() as u64);
+        writeln!(out, "{}", messages::fill("{}, {} each:", &[&files, &format::human_size(*size)]))?;
+        for path in group {
+            writeln!(out, "  {}", path.strip_prefix(root).unwrap_or(path).display())?;
+        }
+    }
+    Ok(())
+}
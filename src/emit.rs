@@ -0,0 +1,267 @@
+//! The machine-readable outputs (`--output csv|json|yaml|dot`): one record per
+//! entry, with the fields chosen by `--fields`; or for DOT, a graph of
+//! the directories. `--porcelain` has fixed fields instead, which are
+//! not going to change.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::columns::{CellFormat, Column, Value};
+use crate::entry::EnrichedEntry;
+use crate::format::{self, TimeStyle};
+
+/// How the entries are printed (`--output`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Output {
+
+    /// for humans, as ls does
+    Table,
+    Csv,
+
+    /// a single array of objects, over all the listed directories
+    Json,
+
+    /// the same as JSON, as a YAML sequence of mappings
+    Yaml,
+
+    /// a Graphviz graph of the directories, printed at the end
+    Dot,
+
+    /// for scripts, in the given version of the format, see `porcelain_record()`
+    Porcelain(u8)
+}
+
+impl Output {
+
+    pub fn parse(s: &str) -> Option<Output> {
+        match s {
+            "table" => { Some(Output::Table) }
+            "csv" => { Some(Output::Csv) }
+            "json" => { Some(Output::Json) }
+            "yaml" => { Some(Output::Yaml) }
+            "dot" => { Some(Output::Dot) }
+            _ => { None }
+        }
+    }
+
+    pub fn is_machine(&self) -> bool {
+        *self != Output::Table
+    }
+}
+
+/// Set once the first JSON (or YAML) record is out, so the others get commas.
+static HAD_RECORDS: AtomicBool = AtomicBool::new(false);
+
+/// The directories seen by the DOT output, and what links them.
+struct Graph {
+
+    /// for each directory, its label and the bytes in the files right in it
+    nodes: BTreeMap<PathBuf, (String, u64)>,
+
+    /// (parent, child)
+    edges: Vec<(PathBuf, PathBuf)>
+}
+
+static GRAPH: Mutex<Graph> = Mutex::new(Graph { nodes: BTreeMap::new(), edges: Vec::new() });
+
+/// Adds the entry to the graph: a node if it is a directory, and its
+/// size to its directory otherwise.
+fn add_to_graph(e: &EnrichedEntry) {
+    let Some(parent) = e.path.parent() else {
+        return;
+    };
+    let mut graph = GRAPH.lock().unwrap();
+    let parent_node = graph.nodes.entry(parent.to_path_buf())
+        .or_insert_with(|| (parent.display().to_string(), 0));
+    if !e.entry.is_directory() {
+        parent_node.1 += e.size();
+        return;
+    }
+    graph.nodes.entry(e.path.clone()).or_insert_with(|| (e.entry.get_name().to_string(), 0));
+    graph.edges.push((parent.to_path_buf(), e.path.clone()));
+}
+
+/// The color of a directory holding this much, between white for nothing
+/// and red for the most; the scale is logarithmic.
+fn size_color(bytes: u64, most: u64) -> String {
+    let fraction = (bytes as f64).ln_1p() / (most as f64).ln_1p().max(1.0);
+    let other = 255 - (fraction * 255.0) as u8;
+    format!("#ff{other:02x}{other:02x}")
+}
+
+fn print_graph(out: &mut dyn Write, with_sizes: bool) -> io::Result<()> {
+    let graph = GRAPH.lock().unwrap();
+
+    // the sizes of whole subtrees, summed up from the deepest directories
+    let mut totals: BTreeMap<&Path, u64> = graph.nodes.iter().map(|(p, (_, bytes))| (p.as_path(), *bytes)).collect();
+    let mut paths: Vec<&Path> = totals.keys().copied().collect();
+    paths.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+    for path in paths {
+        let bytes = totals[path];
+        if let Some(parent_total) = path.parent().and_then(|p| totals.get_mut(p)) {
+            *parent_total += bytes;
+        }
+    }
+    let most = totals.values().copied().max().unwrap_or(0);
+
+    writeln!(out, "digraph tree {{")?;
+    writeln!(out, "  node [shape=folder];")?;
+    for (path, (label, _)) in &graph.nodes {
+        let id = json_string(&path.to_string_lossy());
+        if with_sizes {
+            let bytes = totals[path.as_path()];
+            let label = json_string(&format!("{label}\n{}", format::human_size(bytes)));
+            writeln!(out, "  {id} [label={label}, style=filled, fillcolor=\"{}\"];", size_color(bytes, most))?;
+        } else {
+            writeln!(out, "  {id} [label={}];", json_string(label))?;
+        }
+    }
+    for (parent, child) in &graph.edges {
+        writeln!(out, "  {} -> {};", json_string(&parent.to_string_lossy()), json_string(&child.to_string_lossy()))?;
+    }
+    writeln!(out, "}}")
+}
+
+/// The line of the porcelain output for the entry, with tab-separated
+/// fields: the type ('-', 'd', 'l', 'p', 's', 'c', 'b' or '?'), the
+/// permissions in octal, the size in bytes, the modification time in
+/// seconds since the epoch (or in RFC 3339, if asked for), and the name
+/// escaped as `format::escape_name()` does. Version 2 has one more field
+/// before the name: "1" if anything in it was escaped, "0" if it is as
+/// it is on disk. Fields that are not known are empty.
+///
+/// Version 1 stays as it was before version 2 came: it escapes the name
+/// as it is on disk only where that is the displayed name, so a name that
+/// is not UTF-8 is still "???" there.
+fn porcelain_record(e: &EnrichedEntry, version: u8, time_style: TimeStyle) -> String {
+    let raw_name = match version {
+        1 => {
+            let displayed = e.entry.get_name();
+            e.path.file_name()
+                .map(|n| n.as_bytes())
+                .filter(|raw| String::from_utf8_lossy(raw) == displayed)
+                .unwrap_or(displayed.as_bytes())
+        }
+        _ => { e.raw_name() }
+    };
+    let mut name = format::escape_name(raw_name);
+    if version >= 2 {
+        let escaped = if name.as_bytes() == raw_name { "0" } else { "1" };
+        name = format!("{escaped}\t{name}");
+    }
+    match &e.metadata {
+        Some(m) => {
+            let mtime = match time_style {
+                TimeStyle::Rfc3339 => { format::rfc3339_time(m.mtime(), m.mtime_nsec()) }
+                TimeStyle::Default | TimeStyle::Unix => { m.mtime().to_string() }
+            };
+            format!("{}\t{:04o}\t{}\t{mtime}\t{name}", format::type_char(m.mode()), m.mode() & 0o7777, m.size())
+        }
+        None => { format!("?\t\t\t\t{name}") }
+    }
+}
+
+/// Quotes a CSV field if needed, as RFC 4180 describes.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => { quoted.push_str("\\\"") }
+            '\\' => { quoted.push_str("\\\\") }
+            '\n' => { quoted.push_str("\\n") }
+            '\r' => { quoted.push_str("\\r") }
+            '\t' => { quoted.push_str("\\t") }
+            c if (c as u32) < 0x20 => { quoted.push_str(&format!("\\u{:04x}", c as u32)) }
+            c => { quoted.push(c) }
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The value as JSON; it is valid YAML as well.
+fn json_value(value: Value) -> String {
+    match value {
+        Value::Number(n) => { n.to_string() }
+        Value::Text(text) => { json_string(&text) }
+        Value::Missing => { "null".to_string() }
+    }
+}
+
+/// Prints what comes before the records: the CSV header, or the
+/// opening bracket of the JSON array.
+pub fn print_start(out: &mut dyn Write, output: Output, fields: &[Column]) -> io::Result<()> {
+    HAD_RECORDS.store(false, Ordering::Relaxed);
+    match output {
+        Output::Table | Output::Yaml | Output::Dot | Output::Porcelain(_) => { Ok(()) }
+        Output::Csv => {
+            let keys: Vec<&str> = fields.iter().map(|f| f.key()).collect();
+            writeln!(out, "{}", keys.join(","))
+        }
+        Output::Json => { write!(out, "[") }
+    }
+}
+
+pub fn print_record(out: &mut dyn Write, output: Output, fields: &[Column],
+                    e: &EnrichedEntry, fmt: &CellFormat) -> io::Result<()> {
+    match output {
+        Output::Table => { Ok(()) }
+        Output::Dot => {
+            add_to_graph(e);
+            Ok(())
+        }
+        Output::Porcelain(version) => { writeln!(out, "{}", porcelain_record(e, version, fmt.time_style)) }
+        Output::Csv => {
+            let values: Vec<String> = fields.iter()
+                .map(|f| match f.value(e, fmt) {
+                    Value::Number(n) => { n.to_string() }
+                    Value::Text(text) => { csv_field(&text) }
+                    Value::Missing => { String::new() }
+                })
+                .collect();
+            writeln!(out, "{}", values.join(","))
+        }
+        Output::Json => {
+            let members: Vec<String> = fields.iter()
+                .map(|f| format!("{}: {}", json_string(f.key()), json_value(f.value(e, fmt))))
+                .collect();
+            let separator = if HAD_RECORDS.swap(true, Ordering::Relaxed) { "," } else { "" };
+            write!(out, "{separator}\n  {{{}}}", members.join(", "))
+        }
+        Output::Yaml => {
+            HAD_RECORDS.store(true, Ordering::Relaxed);
+            for (i, f) in fields.iter().enumerate() {
+                let indent = if i == 0 { "- " } else { "  " };
+                writeln!(out, "{indent}{}: {}", f.key(), json_value(f.value(e, fmt)))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Prints what comes after all the records; for DOT, the whole graph,
+/// with the directories colored by size if that is one of the fields.
+pub fn print_end(out: &mut dyn Write, output: Output, fields: &[Column]) -> io::Result<()> {
+    match output {
+        Output::Dot => { print_graph(out, fields.contains(&Column::Size)) }
+        Output::Json if HAD_RECORDS.load(Ordering::Relaxed) => { writeln!(out, "\n]") }
+        Output::Json => { writeln!(out, "]") }
+
+        // an empty sequence has to be spelled out
+        Output::Yaml if !HAD_RECORDS.load(Ordering::Relaxed) => { writeln!(out, "[]") }
+        _ => { Ok(()) }
+    }
+}
@@ -0,0 +1,507 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+
+use crate::messages;
+use crate::sys::{Dir, DirEntry, FileType, Metadata};
+use crate::visited::Revisit;
+
+const ICON_ERROR:   &str = "\u{2753}\u{FE0E}";
+const ICON_FILE:    &str = "\u{1F5CE}\u{FE0E} ";
+const ICON_DIRECTORY:  &str = "\u{1F4C1}\u{FE0E}";
+const ICON_SYMLINK: &str = "\u{1F517}\u{FE0E}";
+#[allow(dead_code)]
+const ICON_EMPTY_FILE: &str = "\u{2B55}\u{FE0E}";
+const ICON_SOCKET:  &str = "\u{1F50C}\u{FE0E}";
+const ICON_PIPE:    &str = "\u{1F6B0}\u{FE0E}";
+#[allow(dead_code)]
+const ICON_TEXT_FILE: &str = "\u{1F5D2}\u{FE0E}";
+const ICON_CHAR_DEVICE: &str = "\u{1F5A8}\u{FE0E}";
+const ICON_BLOCK_DEVICE: &str = "\u{1F4BF}\u{FE0E}";
+const ICON_DISK:    &str = "\u{1F5D4}\u{FE0E}";
+const ICON_DEV_NULL:  &str = "\u{1F6BD}\u{FE0E}";
+const ICON_TTY:     &str = "\u{1F4BB}\u{FE0E}";
+const ICON_WHITEOUT: &str = "\u{1F5D1}\u{FE0E}";
+
+/// Major number of a device id (as glibc's major()).
+pub fn dev_major(dev_id: u64) -> u64 {
+    ((dev_id >> 8) & 0xfff) | ((dev_id >> 32) & !0xfff)
+}
+
+/// Minor number of a device id (as glibc's minor()).
+pub fn dev_minor(dev_id: u64) -> u64 {
+    (dev_id & 0xff) | ((dev_id >> 12) & !0xff)
+}
+
+/// What kind of file an entry is, with what only that kind has; the
+/// `dev_id` of devices is `None` when it could not be read.
+#[derive(PartialEq, Eq, Debug)]
+pub enum EntryKind {
+    Unknown,
+    Regular,
+    Directory,
+    Symlink {
+        target: String
+    },
+    Pipe,
+    Socket,
+    CharDevice {
+        dev_id: Option<u64>
+    },
+    BlockDevice {
+        dev_id: Option<u64>
+    }
+}
+
+/// A single entry of the listing we will produce.
+#[derive(PartialEq, Eq, Debug)]
+pub struct ListingEntry {
+    pub name: String,
+    pub icon: String,
+    pub kind: EntryKind
+}
+
+impl ListingEntry {
+
+    fn new(name: &str, icon: &str, kind: EntryKind) -> ListingEntry {
+        ListingEntry { name: name.to_string(), icon: icon.to_string(), kind }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_icon(&self) -> &str {
+        &self.icon
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.kind == EntryKind::Directory
+    }
+
+    /// Whether this is a leftover of editors or tools: backups ("*~",
+    /// "#*#"), swap files, patch leftovers (".orig", ".rej"), or trash.
+    pub fn is_backup(&self) -> bool {
+        let name = self.get_name();
+        name.ends_with('~')
+            || (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+            || [".swp", ".orig", ".rej"].iter().any(|ext| name.ends_with(ext))
+            || name == ".Trash" || name.starts_with(".Trash-")
+    }
+
+    /// Whether this is an overlayfs whiteout: a character device 0/0
+    /// marking a file deleted in a container layer (a device whose numbers
+    /// could not be read is not taken for one).
+    pub fn is_whiteout(&self) -> bool {
+        self.kind == EntryKind::CharDevice { dev_id: Some(0) }
+    }
+
+    /// Returns the extension of the name (the part after the last dot),
+    /// or `None` if there is none; a leading dot does not count.
+    pub fn get_extension(&self) -> Option<&str> {
+        let name = self.get_name();
+        match name.rfind('.') {
+            Some(pos) if pos > 0 && pos + 1 < name.len() => { Some(&name[pos + 1..]) }
+            _ => { None }
+        }
+    }
+
+    /// Name of the entry type, as shown in the "type" field.
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            EntryKind::Directory => { "directory" }
+            EntryKind::Symlink { .. } => { "symlink" }
+            EntryKind::Regular => { "file" }
+            EntryKind::Pipe => { "pipe" }
+            EntryKind::Socket => { "socket" }
+            EntryKind::CharDevice { .. } => { "char device" }
+            EntryKind::BlockDevice { .. } => { "block device" }
+            EntryKind::Unknown => { "unknown" }
+        }
+    }
+
+    /// Rank of the entry type, used when sorting by type.
+    pub fn type_rank(&self) -> u8 {
+        match self.kind {
+            EntryKind::Directory => { 0 }
+            EntryKind::Symlink { .. } => { 1 }
+            EntryKind::Regular => { 2 }
+            EntryKind::Pipe => { 3 }
+            EntryKind::Socket => { 4 }
+            EntryKind::CharDevice { .. } => { 5 }
+            EntryKind::BlockDevice { .. } => { 6 }
+            EntryKind::Unknown => { 7 }
+        }
+    }
+
+    pub fn new_regular(name: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_FILE, EntryKind::Regular)
+    }
+
+    pub fn new_dir(name: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_DIRECTORY, EntryKind::Directory)
+    }
+
+    pub fn new_symlink(name: &str, target: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_SYMLINK, EntryKind::Symlink { target: target.to_string() })
+    }
+
+    pub fn new_unknown(name: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_ERROR, EntryKind::Unknown)
+    }
+
+    pub fn new_pipe(name: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_PIPE, EntryKind::Pipe)
+    }
+
+    pub fn new_char_device(name: &str, dev_id: Option<u64>) -> ListingEntry {
+        let Some(known_id) = dev_id else {
+            return ListingEntry::new(name, ICON_CHAR_DEVICE, EntryKind::CharDevice { dev_id });
+        };
+        let mut icon = ICON_CHAR_DEVICE;
+
+        // give some specific devices their own icons
+        let dev_major = dev_major(known_id);
+        let dev_minor = dev_minor(known_id);
+        if known_id == 0 {                      // overlayfs whiteouts
+            icon = ICON_WHITEOUT;
+        }
+        else if dev_major == 1 && dev_minor == 3 {   // /dev/null
+            icon = ICON_DEV_NULL;
+        }
+        else if dev_major == 4 {                // oldschool ttys
+            icon = ICON_TTY;
+        }
+        else if dev_major == 5 && (dev_minor == 0 || dev_minor == 1) {      // /dev/tty, /dev/console
+            icon = ICON_TTY;
+        }
+        else if dev_major == 241 {              // disks
+            icon = ICON_DISK;
+        }
+
+        ListingEntry::new(name, icon, EntryKind::CharDevice { dev_id })
+    }
+
+    pub fn new_block_device(name: &str, dev_id: Option<u64>) -> ListingEntry {
+        ListingEntry::new(name, ICON_BLOCK_DEVICE, EntryKind::BlockDevice { dev_id })
+    }
+
+    pub fn new_socket(name: &str) -> ListingEntry {
+        ListingEntry::new(name, ICON_SOCKET, EntryKind::Socket)
+    }
+
+    pub fn from_dentry(dentry: &fs::DirEntry) -> ListingEntry {
+
+        // get the file name; this may fail, in which case
+        // we print "???" to at least show that there is something
+        let name = dentry.file_name().into_string();
+        if name.is_err() {
+            return ListingEntry::new_unknown("???");
+        }
+        let name = name.unwrap();
+
+        // identify file type; this can also fail, in which case
+        // we print the name and unknown type
+        let dentry_file_type = dentry.file_type();
+        if dentry_file_type.is_err() {
+            return ListingEntry::new_unknown(&name);
+        }
+        let dentry_file_type = dentry_file_type.unwrap();
+
+        ListingEntry::of_type(&name, dentry_file_type.into(), &|| fs::read_link(dentry.path()),
+            &|| dentry.metadata().ok().map(|m| m.rdev()))
+    }
+
+    /// Builds the entry for a path given directly rather than found in
+    /// a directory; `name` is what will be displayed.
+    pub fn from_path(name: &str, path: &Path) -> ListingEntry {
+        match fs::symlink_metadata(path) {
+            Err(_) => { ListingEntry::new_unknown(name) }
+            Ok(metadata) => {
+                ListingEntry::of_type(name, metadata.file_type().into(), &|| fs::read_link(path), &|| Some(metadata.rdev()))
+            }
+        }
+    }
+
+    /// Builds the entry of the given type; `target` is only called for
+    /// symlinks, and `dev_id` only for devices.
+    fn of_type(name: &str, file_type: FileType, target: &dyn Fn() -> io::Result<PathBuf>,
+               dev_id: &dyn Fn() -> Option<u64>) -> ListingEntry {
+        match file_type {
+            FileType::Directory => { ListingEntry::new_dir(name) }
+            FileType::Symlink => {
+                match target() {
+                    Ok(target) => { ListingEntry::new_symlink(name, target.to_str().unwrap_or("???")) }
+                    Err(_) => { ListingEntry::new_symlink(name, "???") }
+                }
+            }
+            FileType::Pipe => { ListingEntry::new_pipe(name) }
+            FileType::CharDevice => { ListingEntry::new_char_device(name, dev_id()) }
+            FileType::BlockDevice => { ListingEntry::new_block_device(name, dev_id()) }
+            FileType::Socket => { ListingEntry::new_socket(name) }
+            FileType::Regular => { ListingEntry::new_regular(name) }
+        }
+    }
+}
+
+/// The icon and the name, as in the plain listing (without colors, and
+/// without the icons of the config file).
+impl fmt::Display for ListingEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.get_icon(), self.get_name())
+    }
+}
+
+/// A short description of an error, as shown next to the entry.
+pub fn error_reason(kind: io::ErrorKind) -> &'static str {
+    match kind {
+        io::ErrorKind::PermissionDenied => { messages::tr("access denied") }
+        io::ErrorKind::TimedOut => { messages::tr("timed out") }
+        io::ErrorKind::OutOfMemory => { messages::tr("out of memory") }
+        _ => { messages::tr("I/O error") }
+    }
+}
+
+/// A listing entry together with the metadata needed to sort (and later
+/// filter and format) it. The metadata describes the entry itself, i.e.
+/// symlinks are not followed; it is `None` if it could not be read.
+pub struct EnrichedEntry {
+    pub entry: ListingEntry,
+
+    /// where the entry was found; empty if not even that is known
+    pub path: PathBuf,
+
+    /// the open directory the entry is in, for looking things up in it
+    /// relative to that rather than by path; `None` for entries given by path
+    pub dir: Option<Dir>,
+
+    pub metadata: Option<Metadata>,
+
+    /// reading the metadata took too long (see `--metadata-timeout`)
+    pub stat_timed_out: bool,
+
+    /// the entry was deleted after the directory was read, but before
+    /// its metadata was
+    pub vanished: bool,
+
+    /// why the entry or its metadata could not be read (other than
+    /// that it was deleted)
+    pub error: Option<io::ErrorKind>,
+
+    /// the recursive listing has been in this directory already
+    pub revisit: Option<Revisit>
+}
+
+impl EnrichedEntry {
+
+    pub fn from_dentry(dentry: &fs::DirEntry) -> EnrichedEntry {
+        let metadata = dentry.metadata();
+        let failure = metadata.as_ref().err().map(io::Error::kind);
+        EnrichedEntry {
+            entry: ListingEntry::from_dentry(dentry),
+            path: dentry.path(),
+            dir: None,
+            metadata: metadata.ok().map(Metadata::from),
+            stat_timed_out: false,
+            vanished: failure == Some(io::ErrorKind::NotFound),
+            error: failure.filter(|kind| *kind != io::ErrorKind::NotFound),
+            revisit: None
+        }
+    }
+
+    /// The entry of the given name in an open directory, found at `parent`;
+    /// its metadata is read relative to the directory rather than by path.
+    pub fn in_dir(dir: &Dir, parent: &Path, name: &OsStr) -> EnrichedEntry {
+        let metadata = dir.metadata_at(name);
+        let path = parent.join(name);
+        let display_name = name.to_str().unwrap_or("???");
+        let entry = match &metadata {
+            Ok(m) => {
+                ListingEntry::of_type(display_name, m.file_type(), &|| dir.read_link_at(name), &|| Some(m.rdev()))
+            }
+            Err(_) => { ListingEntry::new_unknown(display_name) }
+        };
+        let failure = metadata.as_ref().err().map(io::Error::kind);
+        EnrichedEntry {
+            vanished: failure == Some(io::ErrorKind::NotFound),
+            error: failure.filter(|kind| *kind != io::ErrorKind::NotFound),
+            entry,
+            path,
+            dir: Some(dir.clone()),
+            metadata: metadata.ok(),
+            stat_timed_out: false,
+            revisit: None
+        }
+    }
+
+    /// An entry for a path given directly; the path is also its name.
+    pub fn from_path(path: &Path) -> EnrichedEntry {
+        EnrichedEntry::named(&path.to_string_lossy(), path)
+    }
+
+    /// An entry for a path, displayed under the given name.
+    pub fn named(name: &str, path: &Path) -> EnrichedEntry {
+        EnrichedEntry {
+            entry: ListingEntry::from_path(name, path),
+            path: path.to_path_buf(),
+            dir: None,
+            metadata: fs::symlink_metadata(path).ok().map(Metadata::from),
+            stat_timed_out: false,
+            vanished: false,
+            error: None,
+            revisit: None
+        }
+    }
+
+    /// An entry for a directory that is open already (e.g. given as
+    /// "fd:N"), displayed under the given name, which is also its path.
+    pub fn of_dir(name: &str, dir: &Dir) -> EnrichedEntry {
+        let no_target = || Err(io::ErrorKind::InvalidInput.into());
+        EnrichedEntry {
+            entry: ListingEntry::of_type(name, FileType::Directory, &no_target, &|| None),
+            path: PathBuf::from(name),
+            dir: None,
+            metadata: dir.metadata().ok(),
+            stat_timed_out: false,
+            vanished: false,
+            error: None,
+            revisit: None
+        }
+    }
+
+    /// The entry as the directory tells it, without reading its metadata;
+    /// only where the directory does not tell the type is it read.
+    pub fn in_dir_without_metadata(dir: &Dir, parent: &Path, dentry: &DirEntry) -> EnrichedEntry {
+        let Some(file_type) = dentry.file_type else {
+            return EnrichedEntry::in_dir(dir, parent, &dentry.name);
+        };
+        let name = &dentry.name;
+        let entry = ListingEntry::of_type(name.to_str().unwrap_or("???"), file_type, &|| dir.read_link_at(name),
+            &|| dir.metadata_at(name).ok().map(|m| m.rdev()));
+        EnrichedEntry {
+            entry,
+            path: parent.join(name),
+            dir: Some(dir.clone()),
+            metadata: None,
+            stat_timed_out: false,
+            vanished: false,
+            error: None,
+            revisit: None
+        }
+    }
+
+    /// An entry whose metadata could not be read in time; nothing else
+    /// is looked up, as that would likely hang too.
+    pub fn timed_out(parent: &Path, name: &OsStr) -> EnrichedEntry {
+        EnrichedEntry {
+            entry: ListingEntry::new_unknown(&name.to_string_lossy()),
+            path: parent.join(name),
+            dir: None,
+            metadata: None,
+            stat_timed_out: true,
+            vanished: false,
+            error: None,
+            revisit: None
+        }
+    }
+
+    /// The "???" entry for what the directory could not read.
+    pub fn unreadable(err: &io::Error) -> EnrichedEntry {
+        EnrichedEntry { error: Some(err.kind()), ..EnrichedEntry::new_unknown("???") }
+    }
+
+    pub fn new_unknown(name: &str) -> EnrichedEntry {
+        EnrichedEntry {
+            entry: ListingEntry::new_unknown(name),
+            path: PathBuf::new(),
+            dir: None,
+            metadata: None,
+            stat_timed_out: false,
+            vanished: false,
+            error: None,
+            revisit: None
+        }
+    }
+
+    /// The name as it is on disk, which need not be UTF-8 as `get_name()`
+    /// is (names that are not are shown as "???"); the displayed name if
+    /// it was made up for the listing, as "." and ".." are.
+    pub fn raw_name(&self) -> &[u8] {
+        let name = self.entry.get_name();
+        if self.path.as_os_str().to_string_lossy() == name {
+            return self.path.as_os_str().as_bytes();
+        }
+        match self.path.file_name() {
+            Some(raw) if raw.to_string_lossy() == name || (raw.to_str().is_none() && name == "???") => {
+                raw.as_bytes()
+            }
+            _ => { name.as_bytes() }
+        }
+    }
+
+    /// The open directory the entry is in, and its name there.
+    fn in_parent(&self) -> Option<(&Dir, &OsStr)> {
+        Some((self.dir.as_ref()?, self.path.file_name()?))
+    }
+
+    /// Opens the entry, a directory, relative to the directory it is in
+    /// (symlinks are not followed there), or else by path.
+    pub fn open_dir(&self) -> io::Result<Dir> {
+        match self.in_parent() {
+            Some((dir, name)) => { dir.open_at(name) }
+            None => { Dir::open(&self.path) }
+        }
+    }
+
+    /// Whether this is a symlink whose target does not exist.
+    pub fn is_broken_symlink(&self) -> bool {
+        if !matches!(self.entry.kind, EntryKind::Symlink { .. }) {
+            return false;
+        }
+        match self.in_parent() {
+            Some((dir, name)) => { dir.metadata_following_at(name).is_err() }
+            None => { fs::metadata(&self.path).is_err() }
+        }
+    }
+
+    /// Whether this is a regular file of zero size, or a directory
+    /// without any entries (like `find -empty`).
+    pub fn is_empty(&self) -> bool {
+        match self.entry.kind {
+            EntryKind::Regular => { self.metadata.is_some() && self.size() == 0 }
+            EntryKind::Directory => {
+                self.open_dir().and_then(|dir| dir.entries()).is_ok_and(|mut entries| entries.next().is_none())
+            }
+            _ => { false }
+        }
+    }
+
+    /// Size in bytes, or 0 if unknown.
+    pub fn size(&self) -> u64 {
+        self.metadata.as_ref().map_or(0, |m| m.size())
+    }
+
+    /// Modification time in seconds since the epoch, or 0 if unknown.
+    pub fn mtime(&self) -> i64 {
+        self.metadata.as_ref().map_or(0, |m| m.mtime())
+    }
+
+    /// Numeric id of the owner, if known.
+    pub fn uid(&self) -> Option<u32> {
+        self.metadata.as_ref().map(|m| m.uid())
+    }
+
+    /// Numeric id of the group, if known.
+    pub fn gid(&self) -> Option<u32> {
+        self.metadata.as_ref().map(|m| m.gid())
+    }
+
+    /// Permission bits (including setuid, setgid and sticky), if known.
+    pub fn permissions(&self) -> Option<u32> {
+        self.metadata.as_ref().map(|m| m.mode() & 0o7777)
+    }
+}
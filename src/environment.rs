@@ -0,0 +1,47 @@
+//! What the output depends on besides the options and the files: the
+//! clock, the terminal and the environment variables. The renderer reads
+//! them through an `Environment`, so that they can be made up, e.g. to
+//! get the same output every time in tests.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use crate::format;
+use crate::sys;
+
+pub trait Environment {
+
+    /// The current time, in seconds since the epoch.
+    fn now(&self) -> i64;
+
+    /// The number of columns of the terminal on stdout, if it is one.
+    fn terminal_width(&self) -> Option<usize>;
+
+    /// Whether stdout is a terminal.
+    fn is_tty(&self) -> bool;
+
+    /// The value of the environment variable, if it is set (as UTF-8).
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// The environment of the running process.
+pub struct System;
+
+impl Environment for System {
+
+    fn now(&self) -> i64 {
+        format::now()
+    }
+
+    fn terminal_width(&self) -> Option<usize> {
+        sys::terminal_width()
+    }
+
+    fn is_tty(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+}
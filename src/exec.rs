@@ -0,0 +1,144 @@
+//! Running a command for the listed entries (`--exec`, `--exec-batch`).
+
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+use std::process::Command;
+
+use crate::entry::EnrichedEntry;
+use crate::messages;
+
+/// The placeholder replaced by the path(s).
+const PLACEHOLDER: &[u8] = b"{}";
+
+/// Splits the command into words like a shell would, as far as whitespace,
+/// single and double quotes and backslashes are concerned; nothing else
+/// (variables, globs, pipes) is interpreted.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => { break }
+                        Some(c) => { word.push(c) }
+                        None => { return Err(format!("Unterminated quote in '{command}'")) }
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => { break }
+                        Some('\\') => {
+                            match chars.next() {
+                                Some(c) => { word.push(c) }
+                                None => { return Err(format!("Unterminated quote in '{command}'")) }
+                            }
+                        }
+                        Some(c) => { word.push(c) }
+                        None => { return Err(format!("Unterminated quote in '{command}'")) }
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() { word.push(c) }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    if words.is_empty() {
+        return Err("The command to execute is empty".to_string());
+    }
+    Ok(words)
+}
+
+/// Replaces every "{}" in the word with the path.
+fn substitute(word: &str, path: &Path) -> OsString {
+    let word = word.as_bytes();
+    let path = path.as_os_str().as_bytes();
+    let mut result = Vec::with_capacity(word.len() + path.len());
+    let mut i = 0;
+    while i < word.len() {
+        if word[i..].starts_with(PLACEHOLDER) {
+            result.extend_from_slice(path);
+            i += PLACEHOLDER.len();
+        } else {
+            result.push(word[i]);
+            i += 1;
+        }
+    }
+    OsString::from_vec(result)
+}
+
+fn has_placeholder(word: &str) -> bool {
+    word.contains("{}")
+}
+
+/// Runs the command; returns whether it ran and succeeded.
+fn run(args: &[OsString]) -> bool {
+    match Command::new(&args[0]).args(&args[1..]).status() {
+        Ok(status) => { status.success() }
+        Err(err) => {
+            eprintln!("{}", messages::fill("Could not run '{}': {}", &[&args[0].to_string_lossy(), &err]));
+            false
+        }
+    }
+}
+
+/// Runs the command once per entry, with "{}" replaced by its path (the
+/// path is appended if there is no "{}"). Returns whether all succeeded.
+pub fn exec_each(words: &[String], entries: &[&EnrichedEntry]) -> bool {
+    let append = !words.iter().any(|w| has_placeholder(w));
+    let mut success = true;
+    for e in entries {
+        let mut args: Vec<OsString> = words.iter().map(|w| substitute(w, &e.path)).collect();
+        if append {
+            args.push(e.path.clone().into_os_string());
+        }
+        success &= run(&args);
+    }
+    success
+}
+
+/// Runs the command once for all entries: a word that is just "{}" is
+/// replaced by all the paths (and they are appended if there is none).
+pub fn exec_batch(words: &[String], entries: &[&EnrichedEntry]) -> bool {
+    if entries.is_empty() {
+        return true;
+    }
+    let paths = entries.iter().map(|e| e.path.as_os_str());
+    let mut args: Vec<OsString> = Vec::new();
+    let mut substituted = false;
+    for w in words {
+        if w == "{}" {
+            args.extend(paths.clone().map(OsStr::to_os_string));
+            substituted = true;
+        } else {
+            args.push(OsString::from(w));
+        }
+    }
+    if !substituted {
+        args.extend(paths.map(OsStr::to_os_string));
+    }
+    run(&args)
+}
@@ -0,0 +1,111 @@
+//! The filesystem an entry lives on (`--fs`), from the magic number
+//! statfs() gives; remote and memory-backed ones are flagged, as they
+//! explain entries that are slow or vanish on reboot.
+
+use std::path::Path;
+
+use crate::entry::{EnrichedEntry, EntryKind};
+use crate::sys;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Backing {
+    Disk,
+    Remote,
+    Memory
+}
+
+/// The names of the filesystems by magic number (see statfs(2)).
+const FILESYSTEMS: [(u64, &str, Backing); 30] = [
+    (0xef53, "ext4", Backing::Disk),
+    (0x9123683e, "btrfs", Backing::Disk),
+    (0x58465342, "xfs", Backing::Disk),
+    (0x2fc12fc1, "zfs", Backing::Disk),
+    (0xf2f52010, "f2fs", Backing::Disk),
+    (0x4d44, "vfat", Backing::Disk),
+    (0x2011bab0, "exfat", Backing::Disk),
+    (0x5346544e, "ntfs", Backing::Disk),
+    (0x9660, "iso9660", Backing::Disk),
+    (0x73717368, "squashfs", Backing::Disk),
+    (0x794c7630, "overlay", Backing::Disk),
+    (0xf15f, "ecryptfs", Backing::Disk),
+    (0x3153464a, "jfs", Backing::Disk),
+    (0x52654973, "reiserfs", Backing::Disk),
+    (0x6969, "nfs", Backing::Remote),
+    (0xff534d42, "cifs", Backing::Remote),
+    (0xfe534d42, "smb2", Backing::Remote),
+    (0x00c36400, "ceph", Backing::Remote),
+    (0x01021997, "9p", Backing::Remote),
+    (0x47504653, "gpfs", Backing::Remote),
+    (0x0bd00bd0, "lustre", Backing::Remote),
+    // FUSE may be anything, but is most often sshfs and the like
+    (0x65735546, "fuse", Backing::Remote),
+    (0x01021994, "tmpfs", Backing::Memory),
+    (0x858458f6, "ramfs", Backing::Memory),
+    (0x9fa0, "proc", Backing::Memory),
+    (0x62656572, "sysfs", Backing::Memory),
+    (0x1cd1, "devpts", Backing::Memory),
+    (0x63677270, "cgroup2", Backing::Memory),
+    (0x64626720, "debugfs", Backing::Memory),
+    (0x74726163, "tracefs", Backing::Memory)
+];
+
+/// The type of the filesystem an entry is on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Filesystem {
+    pub magic: u64,
+
+    /// `None` if the magic number is not one we know
+    pub name: Option<&'static str>,
+    pub backing: Backing
+}
+
+impl Filesystem {
+
+    /// The name, or the magic number in hex for unknown filesystems.
+    pub fn name(&self) -> String {
+        match self.name {
+            Some(name) => { name.to_string() }
+            None => { format!("{:#x}", self.magic) }
+        }
+    }
+
+    /// The name, flagged if the filesystem is not on a local disk.
+    pub fn describe(&self) -> String {
+        match self.backing {
+            Backing::Disk => { self.name() }
+            Backing::Remote => { format!("{} (remote)", self.name()) }
+            Backing::Memory => { format!("{} (memory)", self.name()) }
+        }
+    }
+}
+
+/// The filesystem of the entry. A mounted-on directory gives the mounted
+/// filesystem; a symlink gives that of its directory, not of its target.
+pub fn of(e: &EnrichedEntry) -> Option<Filesystem> {
+    let path = match e.entry.kind {
+        EntryKind::Symlink { .. } => {
+            e.path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+        }
+        _ => { &e.path }
+    };
+    let magic = sys::filesystem_magic(path)?;
+    let known = FILESYSTEMS.iter().find(|(m, _, _)| *m == magic);
+    Some(match known {
+        Some((_, name, backing)) => { Filesystem { magic, name: Some(name), backing: *backing } }
+        None => { Filesystem { magic, name: None, backing: Backing::Disk } }
+    })
+}
+
+const PROC_MAGIC: u64 = 0x9fa0;
+const SYSFS_MAGIC: u64 = 0x62656572;
+
+/// Whether the path is on procfs.
+pub fn is_proc(path: &Path) -> bool {
+    sys::filesystem_magic(path) == Some(PROC_MAGIC)
+}
+
+/// Whether the path is on procfs or sysfs, whose files have no
+/// meaningful sizes (they are made up when read).
+pub fn is_pseudo(path: &Path) -> bool {
+    matches!(sys::filesystem_magic(path), Some(PROC_MAGIC) | Some(SYSFS_MAGIC))
+}
@@ -0,0 +1,94 @@
+use crate::category::{self, Category};
+use crate::entry::EnrichedEntry;
+use crate::users;
+
+/// How the permission bits given to `--perm` are compared, as in find(1).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PermMatch {
+
+    /// "MODE": the permissions are exactly these
+    Exact(u32),
+
+    /// "-MODE": all of these bits are set
+    All(u32),
+
+    /// "/MODE": any of these bits is set
+    Any(u32)
+}
+
+impl PermMatch {
+
+    /// Parses an octal mode, optionally prefixed by '-' or '/'.
+    pub fn parse(s: &str) -> Option<PermMatch> {
+        let (kind, digits): (fn(u32) -> PermMatch, &str) = match s.chars().next() {
+            Some('-') => { (PermMatch::All, &s[1..]) }
+            Some('/') => { (PermMatch::Any, &s[1..]) }
+            _ => { (PermMatch::Exact, s) }
+        };
+        match u32::from_str_radix(digits, 8) {
+            Ok(mode) if mode <= 0o7777 => { Some(kind(mode)) }
+            _ => { None }
+        }
+    }
+
+    pub fn matches(&self, permissions: u32) -> bool {
+        match *self {
+            PermMatch::Exact(mode) => { permissions == mode }
+            PermMatch::All(mode) => { permissions & mode == mode }
+
+            // like find, "/000" matches everything
+            PermMatch::Any(mode) => { mode == 0 || permissions & mode != 0 }
+        }
+    }
+}
+
+/// A condition an entry must satisfy to be listed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Owner(u32),
+    NotOwner(u32),
+    Group(u32),
+    Perm(PermMatch),
+    BrokenSymlink,
+    Empty,
+
+    /// regular files of any of the categories
+    Category(Vec<Category>)
+}
+
+impl Filter {
+
+    pub fn parse_owner(s: &str) -> Result<Filter, String> {
+        users::user_id(s).map(Filter::Owner).ok_or_else(|| format!("Unknown user '{s}'"))
+    }
+
+    pub fn parse_group(s: &str) -> Result<Filter, String> {
+        users::group_id(s).map(Filter::Group).ok_or_else(|| format!("Unknown group '{s}'"))
+    }
+
+    pub fn parse_perm(s: &str) -> Result<Filter, String> {
+        PermMatch::parse(s).map(Filter::Perm).ok_or_else(|| format!("Invalid permission mode '{s}'"))
+    }
+
+    pub fn parse_category(s: &str) -> Result<Filter, String> {
+        category::parse_categories(s).map(Filter::Category)
+    }
+
+    /// Entries whose metadata cannot be read never match.
+    pub fn matches(&self, entry: &EnrichedEntry) -> bool {
+        match self {
+            Filter::Owner(uid) => { entry.uid() == Some(*uid) }
+            Filter::NotOwner(uid) => { entry.uid().is_some_and(|u| u != *uid) }
+            Filter::Group(gid) => { entry.gid() == Some(*gid) }
+            Filter::Perm(perm) => { entry.permissions().is_some_and(|p| perm.matches(p)) }
+            Filter::BrokenSymlink => { entry.is_broken_symlink() }
+            Filter::Empty => { entry.is_empty() }
+            Filter::Category(categories) => { category::of(entry).is_some_and(|c| categories.contains(&c)) }
+        }
+    }
+}
+
+/// Checks that the entry satisfies all the filters.
+pub fn matches_all(filters: &[Filter], entry: &EnrichedEntry) -> bool {
+    filters.iter().all(|f| f.matches(entry))
+}
@@ -0,0 +1,328 @@
+//! Formatting of metadata values for the long listing.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::messages;
+use crate::sys;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+];
+
+/// Files modified longer ago than this (half an average Gregorian year)
+/// show the year instead of the time of day, as in ls.
+const RECENT_SECS: i64 = 31_556_952 / 2;
+
+/// The character ls uses for the file type in the permission string.
+pub fn type_char(mode: u32) -> char {
+    match mode & 0o170000 {
+        0o040000 => { 'd' }
+        0o120000 => { 'l' }
+        0o100000 => { '-' }
+        0o010000 => { 'p' }
+        0o140000 => { 's' }
+        0o020000 => { 'c' }
+        0o060000 => { 'b' }
+        _ => { '?' }
+    }
+}
+
+/// Renders the mode as e.g. "drwxr-xr-x", including setuid/setgid/sticky.
+pub fn permission_string(mode: u32) -> String {
+    let mut s = String::with_capacity(10);
+    s.push(type_char(mode));
+
+    // (read bit, write bit, execute bit, special bit, special char)
+    let triples = [
+        (0o400, 0o200, 0o100, 0o4000, 's'),
+        (0o040, 0o020, 0o010, 0o2000, 's'),
+        (0o004, 0o002, 0o001, 0o1000, 't')
+    ];
+    for (r, w, x, special, c) in triples {
+        s.push(if mode & r != 0 { 'r' } else { '-' });
+        s.push(if mode & w != 0 { 'w' } else { '-' });
+        s.push(match (mode & x != 0, mode & special != 0) {
+            (true, true) => { c }
+            (false, true) => { c.to_ascii_uppercase() }
+            (true, false) => { 'x' }
+            (false, false) => { '-' }
+        });
+    }
+    s
+}
+
+/// Renders the permission bits as four octal digits, e.g. "0644".
+pub fn octal_permissions(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+/// The unit the sizes are shown in (`--size-unit`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SizeUnit {
+
+    /// the largest one that keeps the number below 1024, as with `-h`
+    Auto,
+    Kilo,
+    Mega,
+    Giga
+}
+
+impl SizeUnit {
+
+    pub fn parse(s: &str) -> Option<SizeUnit> {
+        match s {
+            "auto" => { Some(SizeUnit::Auto) }
+            "K" | "k" => { Some(SizeUnit::Kilo) }
+            "M" | "m" => { Some(SizeUnit::Mega) }
+            "G" | "g" => { Some(SizeUnit::Giga) }
+            _ => { None }
+        }
+    }
+}
+
+/// Renders a size in a fixed unit, so that the sizes down a column can be
+/// compared at a glance: "0.1M", "3.5M", "1536M". Values are rounded up
+/// as with `human_size()`, so only empty files show as "0".
+pub fn size_in_unit(bytes: u64, unit: SizeUnit) -> String {
+    let (power, suffix) = match unit {
+        SizeUnit::Auto => { return human_size(bytes) }
+        SizeUnit::Kilo => { (1, "K") }
+        SizeUnit::Mega => { (2, "M") }
+        SizeUnit::Giga => { (3, "G") }
+    };
+    if bytes == 0 {
+        return "0".to_string();
+    }
+    let value = bytes as f64 / 1024f64.powi(power);
+    let tenths = (value * 10.0).ceil() / 10.0;
+    if tenths < 10.0 {
+        format!("{tenths:.1}{suffix}")
+    } else {
+        format!("{}{suffix}", value.ceil())
+    }
+}
+
+/// Renders a size the way `ls -h` does: "999", "1.5K", "12M"; values
+/// are rounded up, and those below 10 get one decimal place.
+pub fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let units = ["K", "M", "G", "T", "P", "E"];
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        let rounded = (value * 10.0).ceil() / 10.0;
+        if rounded < 10.0 {
+            return format!("{:.1}{}", rounded, units[unit]);
+        }
+    }
+    let rounded = value.ceil();
+    if rounded >= 1024.0 && unit + 1 < units.len() {
+        return format!("1.0{}", units[unit + 1]);
+    }
+    format!("{}{}", rounded, units[unit])
+}
+
+/// Renders a number with its digits in groups of three: "1,234,567".
+pub fn grouped_number(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() * 4 / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Whether the character takes two cells on the terminal: East Asian
+/// wide characters, and emoji shown as such by default.
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115f | 0x231a..=0x231b | 0x23e9..=0x23ec | 0x23f0 | 0x23f3
+        | 0x25fd..=0x25fe | 0x2614..=0x2615 | 0x2648..=0x2653 | 0x267f | 0x2693
+        | 0x26a1 | 0x26aa..=0x26ab | 0x26bd..=0x26be | 0x26c4..=0x26c5 | 0x26ce
+        | 0x26d4 | 0x26ea | 0x26f2..=0x26f3 | 0x26f5 | 0x26fa | 0x26fd | 0x2705
+        | 0x270a..=0x270b | 0x2728 | 0x274c | 0x274e | 0x2753..=0x2755 | 0x2757
+        | 0x2795..=0x2797 | 0x27b0 | 0x27bf | 0x2b1b..=0x2b1c | 0x2b50 | 0x2b55
+        | 0x2e80..=0x303e | 0x3041..=0x33ff | 0x3400..=0x4dbf | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60 | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f545 | 0x1f57a | 0x1f595..=0x1f596 | 0x1f5a4 | 0x1f5fb..=0x1f64f
+        | 0x1f680..=0x1f6ff | 0x1f900..=0x1faff | 0x20000..=0x3fffd)
+}
+
+/// Whether the character takes no room: combining marks, variation
+/// selectors and the zero-width joiner.
+fn is_zero_width(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036f | 0x200b..=0x200d | 0xfe00..=0xfe0f | 0x20d0..=0x20ff)
+}
+
+/// How many cells of the terminal the text takes, roughly as wcwidth()
+/// would have it; escape sequences are not expected.
+pub fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| if is_zero_width(c) { 0 } else if is_wide(c) { 2 } else { 1 })
+        .sum()
+}
+
+/// Shortens a name to at most `width` characters, replacing the end of
+/// its stem with "…" so that the extension stays visible (unless the
+/// extension alone would take most of the room).
+pub fn truncate_name(name: &str, width: usize) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= width {
+        return name.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let extension_len = match name.rfind('.') {
+        Some(pos) if pos > 0 => { name[pos..].chars().count() }
+        _ => { 0 }
+    };
+    let kept_end = if extension_len * 2 <= width { extension_len } else { 0 };
+    let head: String = chars[..width - 1 - kept_end].iter().collect();
+    let tail: String = chars[chars.len() - kept_end..].iter().collect();
+    format!("{head}…{tail}")
+}
+
+/// Escapes the bytes of a name so that they can be told back exactly,
+/// and so that the name takes a single field of a line: backslashes,
+/// tabs and newlines become `\\`, `\t` and `\n`, other control characters
+/// and bytes that are not UTF-8 become `\xHH`; the rest is kept.
+pub fn escape_name(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => { escaped.push_str("\\\\") }
+                '\t' => { escaped.push_str("\\t") }
+                '\n' => { escaped.push_str("\\n") }
+                c if c.is_control() => {
+                    for b in c.to_string().bytes() {
+                        escaped.push_str(&format!("\\x{b:02x}"));
+                    }
+                }
+                c => { escaped.push(c) }
+            }
+        }
+        for b in chunk.invalid() {
+            escaped.push_str(&format!("\\x{b:02x}"));
+        }
+    }
+    escaped
+}
+
+/// Current time in seconds since the epoch.
+pub fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Renders a timestamp as ls does: "Oct 14 12:34" for recent times,
+/// "Oct 14  2025" for those older than half a year or in the future.
+pub fn format_time(secs: i64, now: i64) -> String {
+    let t = sys::local_time(secs);
+    let month = MONTHS[(t.month as usize).saturating_sub(1) % 12];
+    if secs <= now && now - secs < RECENT_SECS {
+        format!("{} {:>2} {:02}:{:02}", month, t.day, t.hour, t.minute)
+    } else {
+        format!("{} {:>2}  {}", month, t.day, t.year)
+    }
+}
+
+/// Renders a timestamp as e.g. "2025-10-14 12:34:56" in local time,
+/// for the machine-readable outputs.
+pub fn iso_time(secs: i64) -> String {
+    let t = sys::local_time(secs);
+    format!("{}-{:02}-{:02} {:02}:{:02}:{:02}", t.year, t.month, t.day, t.hour, t.minute, t.second)
+}
+
+/// How the times are written (`--time-style`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeStyle {
+
+    /// "Jun 15 12:00" in the listing, "2020-06-15 12:00:00" in the
+    /// machine-readable outputs and seconds since the epoch in porcelain
+    Default,
+
+    /// seconds since the epoch
+    Unix,
+
+    /// "2020-06-15T12:00:00.250000000+02:00", to the nanosecond
+    Rfc3339
+}
+
+impl TimeStyle {
+
+    pub fn parse(s: &str) -> Option<TimeStyle> {
+        match s {
+            "default" => { Some(TimeStyle::Default) }
+            "unix" => { Some(TimeStyle::Unix) }
+            "rfc3339" => { Some(TimeStyle::Rfc3339) }
+            _ => { None }
+        }
+    }
+}
+
+/// Renders a timestamp as RFC 3339 says, in local time with the offset
+/// from UTC ("Z" in UTC itself), e.g. "2025-10-14T12:34:56.500000000+02:00".
+pub fn rfc3339_time(secs: i64, nanos: i64) -> String {
+    let t = sys::local_time(secs);
+    let offset = match t.utc_offset {
+        0 => { "Z".to_string() }
+        offset => {
+            let sign = if offset < 0 { '-' } else { '+' };
+            format!("{sign}{:02}:{:02}", offset.abs() / 3600, offset.abs() % 3600 / 60)
+        }
+    };
+    format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}.{nanos:09}{offset}", t.year, t.month, t.day, t.hour, t.minute, t.second)
+}
+
+/// Timestamps further than this from now (30 days) are shown as dates
+/// even with `--relative-time`, as "97 days ago" says less than a date.
+const RELATIVE_CUTOFF_SECS: i64 = 30 * 24 * 3600;
+
+/// Renders a timestamp relative to now: "just now", "5 minutes ago",
+/// "in 2 hours" (clocks of network filesystems do not always agree),
+/// or the absolute date past the cutoff.
+pub fn relative_time(secs: i64, now: i64) -> String {
+    let delta = now.saturating_sub(secs);
+    if delta.abs() > RELATIVE_CUTOFF_SECS {
+        return format_time(secs, now);
+    }
+    let distance = delta.abs();
+    if distance < 60 {
+        return messages::tr("just now").to_string();
+    }
+    let (count, past, future) = match distance {
+        ..3600 => { (distance / 60, "{} minute ago|{} minutes ago", "in {} minute|in {} minutes") }
+        3600..86400 => { (distance / 3600, "{} hour ago|{} hours ago", "in {} hour|in {} hours") }
+        86400..604800 => { (distance / 86400, "{} day ago|{} days ago", "in {} day|in {} days") }
+        _ => { (distance / 604800, "{} week ago|{} weeks ago", "in {} week|in {} weeks") }
+    };
+    messages::count(if delta < 0 { future } else { past }, count as u64)
+}
+
+/// Renders a horizontal bar of `fraction` (0 to 1) of the full `width`,
+/// using eighth blocks for the fractional end; any non-zero fraction
+/// gets at least the thinnest bar.
+pub fn bar(fraction: f64, width: usize) -> String {
+    const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let mut eighths = (fraction.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+    if eighths == 0 && fraction > 0.0 {
+        eighths = 1;
+    }
+    let mut s = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        s.push(EIGHTHS[eighths % 8]);
+    }
+    s
+}
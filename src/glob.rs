@@ -0,0 +1,113 @@
+//! Shell-style wildcard patterns: `*`, `?` and `[...]` classes
+//! (with ranges, and `!` or `^` to negate).
+
+use std::fs;
+use std::path::Path;
+
+/// Matches a class like "[a-z_]" starting at `pattern[0]`; returns
+/// whether `c` is in it and the length of the class, or `None` if the
+/// class is not closed (then the `[` is taken literally).
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    while i < pattern.len() {
+
+        // a "]" right at the start is part of the class
+        if pattern[i] == ']' && !first {
+            return Some((found != negated, i + 1));
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            found |= pattern[i] <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            found |= pattern[i] == c;
+            i += 1;
+        }
+        first = false;
+    }
+    None
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => { name.is_empty() }
+        Some('*') => {
+            (0..=name.len()).any(|skip| matches_chars(&pattern[1..], &name[skip..]))
+        }
+        Some('?') => { !name.is_empty() && matches_chars(&pattern[1..], &name[1..]) }
+        Some('[') if !name.is_empty() => {
+            match match_class(pattern, name[0]) {
+                Some((true, len)) => { matches_chars(&pattern[len..], &name[1..]) }
+                Some((false, _)) => { false }
+                None => { name[0] == '[' && matches_chars(&pattern[1..], &name[1..]) }
+            }
+        }
+        Some(c) => { name.first() == Some(c) && matches_chars(&pattern[1..], &name[1..]) }
+    }
+}
+
+/// Whether the whole name matches the pattern.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_chars(&pattern, &name)
+}
+
+/// Whether the text has any wildcards in it.
+fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else if prefix.ends_with('/') {
+        format!("{prefix}{name}")
+    } else {
+        format!("{prefix}/{name}")
+    }
+}
+
+/// Expands a path given on the command line the way the shell would,
+/// for shells that leave that to the program: "src/*.rs" becomes the
+/// paths of the files it matches, in order. Names starting with a dot
+/// only match patterns that do too. The path is kept as it is if it
+/// exists as such, or matches nothing (so that the error is reported).
+pub fn expand_path(path: &str) -> Vec<String> {
+    if !has_wildcards(path) || Path::new(path).symlink_metadata().is_ok() {
+        return vec![path.to_string()];
+    }
+    let mut found = vec![if path.starts_with('/') { "/".to_string() } else { String::new() }];
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        let mut next = Vec::new();
+        for prefix in &found {
+            if !has_wildcards(component) {
+                next.push(join(prefix, component));
+                continue;
+            }
+            let Ok(rd) = fs::read_dir(if prefix.is_empty() { "." } else { prefix }) else {
+                continue;
+            };
+            let mut names: Vec<String> = rd.flatten()
+                .filter_map(|d| d.file_name().into_string().ok())
+                .filter(|name| !name.starts_with('.') || component.starts_with('.'))
+                .filter(|name| matches(component, name))
+                .collect();
+            names.sort();
+            next.extend(names.iter().map(|name| join(prefix, name)));
+        }
+        found = next;
+    }
+
+    // "*/" only matches directories, and they keep the slash
+    found.retain(|p| if path.ends_with('/') { Path::new(p).is_dir() } else { Path::new(p).symlink_metadata().is_ok() });
+    if path.ends_with('/') {
+        found.iter_mut().for_each(|p| p.push('/'));
+    }
+    if found.is_empty() { vec![path.to_string()] } else { found }
+}
@@ -0,0 +1,108 @@
+//! The grid layout of names (`-C`, `-x`), computed as GNU ls does: as
+//! many columns as fit, each as wide as its widest name plus a gap.
+
+use std::io::{self, Write};
+
+/// Columns are never narrower than this, gap included.
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// The gap between columns.
+const GAP: usize = 2;
+
+/// The order in which the grid is filled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+
+    /// down the first column, then the next one (`-C`, the default)
+    Down,
+
+    /// along the first row, then the next one (`-x`)
+    Across
+}
+
+/// The column of the grid that the i-th of n cells goes to.
+fn column_of(i: usize, n: usize, columns: usize, direction: Direction) -> usize {
+    match direction {
+        Direction::Down => { i / n.div_ceil(columns) }
+        Direction::Across => { i % columns }
+    }
+}
+
+/// Finds the most columns the cells fit in; returns the width of each
+/// column, the gap included except for the last one.
+fn column_widths(widths: &[usize], line_length: usize, direction: Direction) -> Vec<usize> {
+    let n = widths.len();
+    let max_columns = (line_length / MIN_COLUMN_WIDTH).max(1).min(n);
+    for columns in (1..=max_columns).rev() {
+        let mut column_widths = vec![MIN_COLUMN_WIDTH; columns];
+        for (i, width) in widths.iter().enumerate() {
+            let column = column_of(i, n, columns, direction);
+            let width = width + if column + 1 == columns { 0 } else { GAP };
+            column_widths[column] = column_widths[column].max(width);
+        }
+
+        // like GNU ls, do not use the very last position of the line
+        if columns == 1 || column_widths.iter().sum::<usize>() < line_length {
+            return column_widths;
+        }
+    }
+    vec![MIN_COLUMN_WIDTH]
+}
+
+/// Pads from one position to another; with `tabs`, using tabs where they
+/// fit (8 wide), as GNU ls does.
+fn indent(out: &mut dyn Write, mut from: usize, to: usize, tabs: bool) -> io::Result<()> {
+    const TAB_SIZE: usize = 8;
+    while from < to {
+        if tabs && to / TAB_SIZE > (from + 1) / TAB_SIZE {
+            write!(out, "\t")?;
+            from += TAB_SIZE - from % TAB_SIZE;
+        } else {
+            write!(out, " ")?;
+            from += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the cells, each being the text and its width on the screen;
+/// without a limit to the line length, all of them on a single line.
+pub fn print_grid(out: &mut dyn Write, cells: &[(String, usize)], line_length: Option<usize>,
+                  direction: Direction, tabs: bool) -> io::Result<()> {
+    if cells.is_empty() {
+        return Ok(());
+    }
+    let Some(line_length) = line_length else {
+        let texts: Vec<&str> = cells.iter().map(|(text, _)| text.as_str()).collect();
+        return writeln!(out, "{}", texts.join("  "));
+    };
+    let n = cells.len();
+    let widths: Vec<usize> = cells.iter().map(|(_, width)| *width).collect();
+    let column_widths = column_widths(&widths, line_length, direction);
+    let columns = column_widths.len();
+    let rows = n.div_ceil(columns);
+    for row in 0..rows {
+        let mut position = 0;
+        for (column, column_width) in column_widths.iter().enumerate() {
+            let i = match direction {
+                Direction::Down => { column * rows + row }
+                Direction::Across => { row * columns + column }
+            };
+            let Some((text, width)) = cells.get(i) else { break };
+            write!(out, "{text}")?;
+
+            // no padding after the last name of the row
+            let next = match direction {
+                Direction::Down => { i + rows }
+                Direction::Across => { i + 1 }
+            };
+            if column + 1 == columns || next >= n {
+                break;
+            }
+            indent(out, position + width, position + column_width, tabs)?;
+            position += column_width;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
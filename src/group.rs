@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::category;
+use crate::config;
+use crate::entry::{EnrichedEntry, EntryKind, ListingEntry};
+use crate::format;
+use crate::messages;
+use crate::sys;
+use crate::users;
+
+/// How the listing is split into sections.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GroupBy {
+
+    /// a single section in the sorted order, without header
+    None,
+
+    /// directories first, then everything else, without headers
+    DirsFirst,
+
+    /// everything else first, then directories, without headers
+    DirsLast,
+
+    /// one section per entry type
+    Type,
+
+    /// one section per extension
+    Extension,
+
+    /// one section per owner
+    Owner,
+
+    /// sections by how long ago the entries were modified
+    Age,
+
+    /// one section per category of content (code, images, ...)
+    Category
+}
+
+impl GroupBy {
+
+    pub fn parse(s: &str) -> Option<GroupBy> {
+        match s {
+            "type" => { Some(GroupBy::Type) }
+            "extension" => { Some(GroupBy::Extension) }
+            "owner" => { Some(GroupBy::Owner) }
+            "age" => { Some(GroupBy::Age) }
+            "category" => { Some(GroupBy::Category) }
+            _ => { None }
+        }
+    }
+
+    /// Whether the sections get a header with their title.
+    pub fn has_headers(&self) -> bool {
+        !matches!(self, GroupBy::None | GroupBy::DirsFirst | GroupBy::DirsLast)
+    }
+}
+
+/// The kinds of listing that can each place the directories differently,
+/// as set in the `[directories]` table of the config file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Grid,
+    Long,
+    Tree
+}
+
+impl Mode {
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Mode::Grid => { "grid" }
+            Mode::Long => { "long" }
+            Mode::Tree => { "tree" }
+        }
+    }
+}
+
+/// The grouping for the kind of listing: the one given, unless that is
+/// the default (directories first) and the config file says "first",
+/// "last" or "mixed" for the mode; other values are ignored.
+pub fn for_mode(by: GroupBy, mode: Mode) -> GroupBy {
+    if by != GroupBy::DirsFirst {
+        return by;
+    }
+    let configured = config::get().table("directories")
+        .and_then(|table| table.get(mode.key()))
+        .and_then(|value| value.as_str());
+    match configured {
+        Some("last") => { GroupBy::DirsLast }
+        Some("mixed") => { GroupBy::None }
+        _ => { by }
+    }
+}
+
+/// A section of the listing; the entries keep their sorted order.
+pub struct Group<'a> {
+    pub title: String,
+    pub entries: Vec<&'a EnrichedEntry>
+}
+
+/// Human-readable name of the entry type, in plural.
+pub fn type_title(entry: &ListingEntry) -> &'static str {
+    match entry.kind {
+        EntryKind::Directory => { messages::tr("Directories") }
+        EntryKind::Symlink { .. } => { messages::tr("Symlinks") }
+        EntryKind::Regular => { messages::tr("Regular files") }
+        EntryKind::Pipe => { messages::tr("Pipes") }
+        EntryKind::Socket => { messages::tr("Sockets") }
+        EntryKind::CharDevice { .. } => { messages::tr("Character devices") }
+        EntryKind::BlockDevice { .. } => { messages::tr("Block devices") }
+        EntryKind::Unknown => { messages::tr("Unknown") }
+    }
+}
+
+const DAY_SECS: i64 = 24 * 3600;
+
+/// The section of a modification time, as (key, title): today (the same
+/// local date, or in the future), in the last 7 days, in the last 30 days,
+/// or before.
+fn age_bucket(secs: i64, now: i64) -> (&'static str, &'static str) {
+    let (then, today) = (sys::local_time(secs), sys::local_time(now));
+    let same_day = (then.year, then.month, then.day) == (today.year, today.month, today.day);
+    match now - secs {
+        age if same_day || age < 0 => { ("0", messages::tr("Today")) }
+        age if age < 7 * DAY_SECS => { ("1", messages::tr("This week")) }
+        age if age < 30 * DAY_SECS => { ("2", messages::tr("This month")) }
+        _ => { ("3", messages::tr("Older")) }
+    }
+}
+
+/// Returns the key the sections are ordered by, and the section title.
+fn group_of(entry: &EnrichedEntry, by: GroupBy) -> (String, String) {
+    match by {
+        GroupBy::None => { (String::new(), String::new()) }
+        GroupBy::DirsFirst => {
+            let rank = if entry.entry.is_directory() { "0" } else { "1" };
+            (rank.to_string(), String::new())
+        }
+        GroupBy::DirsLast => {
+            let rank = if entry.entry.is_directory() { "1" } else { "0" };
+            (rank.to_string(), String::new())
+        }
+        GroupBy::Type => {
+            (entry.entry.type_rank().to_string(), type_title(&entry.entry).to_string())
+        }
+        GroupBy::Extension => {
+
+            // entries without extension go last
+            match entry.entry.get_extension() {
+                Some(ext) => { (format!("0{ext}"), format!(".{ext}")) }
+                None => { ("1".to_string(), messages::tr("No extension").to_string()) }
+            }
+        }
+        GroupBy::Owner => {
+            let owner = match entry.uid() {
+                Some(uid) => { users::user_name(uid).map_or_else(|| uid.to_string(), str::to_string) }
+                None => { "???".to_string() }
+            };
+            (owner.clone(), owner)
+        }
+        GroupBy::Age => {
+            let (key, title) = match &entry.metadata {
+                Some(m) => { age_bucket(m.mtime(), format::now()) }
+                None => { ("4", messages::tr("Unknown age")) }
+            };
+            (key.to_string(), title.to_string())
+        }
+        GroupBy::Category => {
+
+            // entries without a category go last
+            match category::of(entry) {
+                Some(c) => { (c.rank().to_string(), messages::tr(c.title()).to_string()) }
+                None => { ("~".to_string(), messages::tr("Other").to_string()) }
+            }
+        }
+    }
+}
+
+/// Splits the (already sorted) entries into sections.
+pub fn group_entries(entries: &[EnrichedEntry], by: GroupBy) -> Vec<Group<'_>> {
+    let mut keyed: Vec<(String, Group)> = Vec::new();
+    let mut index = HashMap::<String, usize>::new();
+    for entry in entries {
+        let (key, title) = group_of(entry, by);
+        match index.get(&key) {
+            Some(i) => { keyed[*i].1.entries.push(entry) }
+            None => {
+                index.insert(key.clone(), keyed.len());
+                keyed.push((key, Group { title, entries: vec![entry] }));
+            }
+        }
+    }
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.into_iter().map(|(_, group)| group).collect()
+}
@@ -0,0 +1,66 @@
+//! Hashing of file contents, spread over several threads.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// How the files are opened, e.g. relative to an open directory.
+pub type Open<'a> = dyn Fn(&Path) -> io::Result<File> + Sync + 'a;
+
+/// Hashes the content of a file. This is not a cryptographic hash; it is
+/// only meant to tell files apart quickly.
+pub fn hash_file(mut file: File) -> io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 { break; }
+        hasher.write(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Hashes all the files using as many threads as there are CPUs;
+/// the results are in the same order as the paths (`None` if a file
+/// could not be read).
+pub fn hash_files(paths: &[PathBuf], open: &Open<'_>) -> Vec<Option<u64>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; paths.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= paths.len() { break; }
+                    let hash = open(&paths[i]).and_then(hash_file).ok();
+                    results.lock().unwrap()[i] = hash;
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// Compares the contents of two files byte by byte.
+pub fn same_content(a: &Path, b: &Path, open: &Open<'_>) -> io::Result<bool> {
+    let mut fa = open(a)?;
+    let mut fb = open(b)?;
+    let mut buf_a = vec![0u8; 64 * 1024];
+    let mut buf_b = vec![0u8; 64 * 1024];
+    loop {
+        let n = fa.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(fb.read(&mut buf_b[..1])? == 0);
+        }
+        if fb.read_exact(&mut buf_b[..n]).is_err() || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Icon overrides from the `[[icons]]` rules of the config file, e.g.
+//!
+//! ```toml
+//! [[icons]]
+//! glob = "*.test.js"
+//! icon = "🧪"
+//!
+//! [[icons]]
+//! name = "Makefile"
+//! icon = "🛠"
+//! ```
+//!
+//! The rules are tried in order and the first one that matches the
+//! name wins; entries matching none get the icon of their category,
+//! or if they have none, of their type.
+
+use std::sync::OnceLock;
+
+use crate::category;
+use crate::config;
+use crate::entry::EnrichedEntry;
+use crate::glob;
+
+/// When to show the icons (`--icons`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IconMode {
+
+    /// only on terminals, as they get in the way of scripts
+    Auto,
+    Always,
+    Never
+}
+
+impl IconMode {
+
+    pub fn parse(s: &str) -> Option<IconMode> {
+        match s {
+            "auto" => { Some(IconMode::Auto) }
+            "always" => { Some(IconMode::Always) }
+            "never" => { Some(IconMode::Never) }
+            _ => { None }
+        }
+    }
+}
+
+enum Pattern {
+    Name(String),
+    Glob(String)
+}
+
+struct Rule {
+    pattern: Pattern,
+    icon: String
+}
+
+impl Rule {
+
+    /// The rule as it is written in the config file.
+    fn describe(&self) -> String {
+        match &self.pattern {
+            Pattern::Name(name) => { format!("name = \"{name}\"") }
+            Pattern::Glob(pattern) => { format!("glob = \"{pattern}\"") }
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match &self.pattern {
+            Pattern::Name(exact) => { exact == name }
+            Pattern::Glob(pattern) => { glob::matches(pattern, name) }
+        }
+    }
+}
+
+/// The rules of the config file; those without an icon or pattern are skipped.
+fn rules() -> &'static Vec<Rule> {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        config::get().array("icons")
+            .filter_map(|table| {
+                let icon = table.get("icon")?.as_str()?.to_string();
+                let pattern = match (table.get("name"), table.get("glob")) {
+                    (Some(name), _) => { Pattern::Name(name.as_str()?.to_string()) }
+                    (None, Some(pattern)) => { Pattern::Glob(pattern.as_str()?.to_string()) }
+                    (None, None) => { return None }
+                };
+                Some(Rule { pattern, icon })
+            })
+            .collect()
+    })
+}
+
+/// The icon of the entry: that of the first matching rule, or else
+/// the one of its category or type.
+pub fn icon(e: &EnrichedEntry) -> &str {
+    let name = e.entry.get_name();
+    match rules().iter().find(|rule| rule.matches(name)) {
+        Some(rule) => { &rule.icon }
+        None => { category::of(e).map_or_else(|| e.entry.get_icon(), |c| c.icon()) }
+    }
+}
+
+/// Which rule gives the entry its icon, for `--debug`.
+pub fn explain(e: &EnrichedEntry) -> String {
+    let name = e.entry.get_name();
+    match rules().iter().position(|rule| rule.matches(name)) {
+        Some(i) => { format!("icon rule #{} ({})", i + 1, rules()[i].describe()) }
+        None => {
+            match category::of(e) {
+                Some(category) => { format!("icon of the category ({})", category.name()) }
+                None => { "icon of the type".to_string() }
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! The `--largest N` report: the biggest files under a directory.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::entry::EntryKind;
+use crate::format;
+use crate::options::{Operand, Options};
+use crate::walk;
+
+pub fn print_largest(out: &mut dyn Write, operand: &Operand, count: usize, options: &Options) -> io::Result<()> {
+
+    // keep only the `count` biggest files seen so far, smallest on top
+    let mut heap = BinaryHeap::<Reverse<(u64, PathBuf)>>::new();
+    let root = Path::new(&operand.path);
+    walk::walk_opened(root, operand.open(), options.recursive, &options.filters, &mut |e| {
+        if e.entry.kind == EntryKind::Regular {
+            heap.push(Reverse((e.size(), e.path.clone())));
+            if heap.len() > count {
+                heap.pop();
+            }
+        }
+    });
+
+    // into_sorted_vec() of Reverse gives the biggest first
+    let files: Vec<(u64, PathBuf)> = heap.into_sorted_vec().into_iter().map(|Reverse(f)| f).collect();
+    let sizes: Vec<String> = files.iter().map(|(size, _)| format::human_size(*size)).collect();
+    let width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    if options.paths.len() > 1 {
+        writeln!(out, "{}:", root.display())?;
+    }
+    for (size, (_, path)) in sizes.iter().zip(&files) {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        writeln!(out, "{size:>width$} {}", relative.display())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! The listing engine of my_ls. It is a library so that the integration
+//! tests can read, sort and render directories without going through
+//! the binary and parsing what it prints. Like the binary, it is for
+//! Linux only (see `sys`).
+
+pub mod badges;
+pub mod category;
+pub mod clipboard;
+pub mod color;
+pub mod columns;
+pub mod config;
+pub mod deadline;
+pub mod defaults;
+pub mod devices;
+pub mod dired;
+pub mod dupes;
+pub mod emit;
+pub mod environment;
+pub mod entry;
+pub mod exec;
+pub mod filesystems;
+pub mod filter;
+pub mod format;
+pub mod glob;
+pub mod grid;
+pub mod group;
+pub mod hash;
+pub mod icons;
+pub mod largest;
+pub mod limit;
+pub mod listing;
+pub mod messages;
+pub mod options;
+pub mod prefetch;
+pub mod progress;
+pub mod random;
+pub mod render;
+pub mod sockets;
+pub mod sort;
+pub mod statfmt;
+pub mod stats;
+pub mod symlinks;
+pub mod sys;
+pub mod timings;
+pub mod totals;
+pub mod trash;
+pub mod usage;
+pub mod users;
+pub mod visited;
+pub mod walk;
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use crate::group::Group;
+
+/// Which part of the sorted listing to show: first `offset` entries are
+/// skipped, then only the first `head` and/or the last `tail` of the rest
+/// are kept.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Window {
+    pub offset: usize,
+    pub head: Option<usize>,
+    pub tail: Option<usize>
+}
+
+impl Window {
+
+    pub fn is_everything(&self) -> bool {
+        self.offset == 0 && self.head.is_none() && self.tail.is_none()
+    }
+
+    /// Returns the indices to keep out of `total` entries.
+    pub fn range(&self, total: usize) -> Range<usize> {
+        let start = self.offset.min(total);
+        let mut end = total;
+        if let Some(head) = self.head {
+            end = end.min(start + head);
+        }
+        let start = match self.tail {
+            Some(tail) => { start.max(end.saturating_sub(tail)) }
+            None => { start }
+        };
+        start..end
+    }
+}
+
+/// Cuts the window out of the listing as it would be printed, i.e. across
+/// all the groups in order; groups that end up empty are dropped.
+pub fn apply_window<'a>(groups: Vec<Group<'a>>, window: &Window) -> Vec<Group<'a>> {
+    if window.is_everything() {
+        return groups;
+    }
+
+    let total = groups.iter().map(|g| g.entries.len()).sum();
+    let range = window.range(total);
+
+    let mut index = 0;
+    let mut result = Vec::new();
+    for mut group in groups {
+        let first = index;
+        index += group.entries.len();
+        group.entries = group.entries.into_iter()
+            .enumerate()
+            .filter(|(i, _)| range.contains(&(first + i)))
+            .map(|(_, e)| e)
+            .collect();
+        if !group.entries.is_empty() {
+            result.push(group);
+        }
+    }
+    result
+}
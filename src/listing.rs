@@ -0,0 +1,49 @@
+//! Reading a directory entry by entry, for programs that use my_ls as a
+//! library: the entries are looked up as they are asked for, rather than
+//! collected into a list first.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::entry::EnrichedEntry;
+use crate::sys::Dir;
+
+/// An open directory whose entries can be read lazily (see `iter()`).
+pub struct DirListing {
+    dir: Dir,
+    path: PathBuf,
+
+    /// read the metadata of every entry along with it
+    metadata: bool
+}
+
+impl DirListing {
+
+    /// Opens the directory at the path; a symlink to one is followed.
+    pub fn open(path: &Path) -> io::Result<DirListing> {
+        Ok(DirListing { dir: Dir::open(path)?, path: path.to_path_buf(), metadata: false })
+    }
+
+    /// Whether the metadata of the entries is read as they are. Without
+    /// it, the entries only have what the directory tells (their names and
+    /// types) and their `metadata` is `None`, which saves a `statx()` per
+    /// entry on most filesystems.
+    pub fn with_metadata(self, metadata: bool) -> DirListing {
+        DirListing { metadata, ..self }
+    }
+
+    /// The entries, in directory order, without "." and ".."; each is
+    /// only read (and its metadata looked up) when it is asked for, and
+    /// the listing entry itself is its `entry`. Like `fs::ReadDir`, it
+    /// ends after an error.
+    pub fn iter(&self) -> io::Result<impl Iterator<Item = io::Result<EnrichedEntry>> + '_> {
+        Ok(self.dir.entries()?.map(|dentry| {
+            let dentry = dentry?;
+            if self.metadata {
+                Ok(EnrichedEntry::in_dir(&self.dir, &self.path, &dentry.name))
+            } else {
+                Ok(EnrichedEntry::in_dir_without_metadata(&self.dir, &self.path, &dentry))
+            }
+        }))
+    }
+}
@@ -1,304 +1,689 @@
 use std::fs;
 use std::env;
-use std::os::unix::fs::MetadataExt;
+use std::ffi::OsString;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::os::unix::fs::FileTypeExt;
-
-const ICON_ERROR:   &'static str = "\u{2753}\u{FE0E}";
-const ICON_FILE:    &'static str = "\u{1F5CE}\u{FE0E} ";
-const ICON_DIRECTORY:  &'static str = "\u{1F4C1}\u{FE0E}";
-const ICON_SYMLINK: &'static str = "\u{1F517}\u{FE0E}";
-const ICON_EMPTY_FILE: &'static str = "\u{2B55}\u{FE0E}";
-const ICON_SOCKET:  &'static str = "\u{1F50C}\u{FE0E}";
-const ICON_PIPE:    &'static str = "\u{1F6B0}\u{FE0E}";
-const ICON_TEXT_FILE: &'static str = "\u{1F5D2}\u{FE0E}";
-const ICON_CHAR_DEVICE: &'static str = "\u{1F5A8}\u{FE0E}";
-const ICON_BLOCK_DEVICE: &'static str = "\u{1F4BF}\u{FE0E}";
-const ICON_DISK:    &'static str = "\u{1F5D4}\u{FE0E}";
-const ICON_DEV_NULL:  &'static str = "\u{1F6BD}\u{FE0E}";
-const ICON_TTY:     &'static str = "\u{1F4BB}\u{FE0E}";
-
-/// A single entry of the listing we will produce.
-#[derive(PartialEq, Eq)]
-enum ListingEntry {
-
-    Unknown {
-        name: String,
-        icon: String
-    },
-    Regular {
-        name: String,
-        icon: String
-    },
-    Directory {
-        name: String,
-        icon: String
-    },
-    Symlink {
-        name: String,
-        target: String,
-        icon: String
-    },
-    Pipe {
-        name: String,
-        icon: String
-    },
-    Socket {
-        name: String,
-        icon: String
-    },
-    CharDevice {
-        name: String,
-        dev_id: u64,
-        icon: String
-    },
-    BlockDevice {
-        name: String,
-        dev_id: u64,
-        icon: String
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use my_ls::{category, clipboard, config, deadline, defaults, dired, dupes, emit, entry, environment, exec, filter};
+use my_ls::{glob, group, largest, limit, messages, options, prefetch, progress, random, render, sort, stats, sys, timings};
+use my_ls::{totals, usage, users, visited, walk};
+
+use deadline::StatWorker;
+use entry::{EnrichedEntry, EntryKind};
+use environment::System;
+use group::GroupBy;
+use messages::Language;
+use options::{Align, Hidden, Operand, Options, ShowErrors, Stale, Summary};
+use progress::Progress;
+use totals::Tally;
+use render::Renderer;
+use sys::{Dir, DirEntry, Entries};
+use timings::Phase;
+
+/// Set when some directory could not be listed, so that we can
+/// report the failure in the exit status.
+static HAD_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Dot files are not shown unless asked for, as in ls; backups
+/// only if not asked to hide them.
+fn is_shown(e: &EnrichedEntry, options: &Options) -> bool {
+    let hidden = options.hidden == Hidden::Hide && e.entry.get_name().starts_with('.');
+    let ignored = options.ignore_backups && e.entry.is_backup();
+    let stale = options.stale == Stale::Skip && e.vanished;
+    !hidden && !ignored && !stale
+}
+
+/// The "." and ".." entries, which `Dir::entries()` leaves out; they are
+/// only listed with `-a`.
+fn dot_entries(query: &Path, options: &Options) -> Vec<EnrichedEntry> {
+    if options.hidden != Hidden::All {
+        return Vec::new();
     }
+    vec![EnrichedEntry::named(".", query), EnrichedEntry::named("..", &query.join(".."))]
 }
 
-impl ListingEntry {
-
-    pub fn get_name(&self) -> String {
-        match self {
-            ListingEntry::Unknown { name, .. } => { name.to_string() }
-            ListingEntry::Regular { name, .. } => { name.to_string() }
-            ListingEntry::Directory { name, .. } => { name.to_string() }
-            ListingEntry::Symlink { name, .. } => { name.to_string() }
-            ListingEntry::Pipe { name, .. } => { name.to_string() }
-            ListingEntry::Socket { name, .. } => { name.to_string() }
-            ListingEntry::CharDevice { name, .. } => { name.to_string() }
-            ListingEntry::BlockDevice { name, .. } => { name.to_string() }
-        }
+/// Unsorted and ungrouped output needs no list at all, unless we must
+/// find out where its tail starts, align the long listing, sum it up,
+/// hand it over to a command, pick from it at random, lay it out in a grid,
+/// sum it up by category, time the phases apart, total up its blocks,
+/// measure the bars of the sizes against the largest, or find out what
+/// most entries are like.
+fn can_stream(options: &Options) -> bool {
+    options.sort_keys.is_empty()
+        && matches!(options.grouping(), GroupBy::None | GroupBy::DirsFirst)
+        && options.window.tail.is_none() && !options.long && !options.stat
+        && options.exec.is_none() && !options.shuffle && options.sample.is_none()
+        && options.grid(&System).is_none() && !options.timings && options.summary.is_none()
+        && !options.blocks && !options.bar && !options.anomalies
+}
+
+/// Whether anything but the names and types of the entries is used. If
+/// not, the types are taken from what the directory gives (d_type), and the
+/// entries are only stat'ed where the filesystem does not tell the type.
+fn needs_metadata(options: &Options, renderer: &Renderer) -> bool {
+    !options.filters.is_empty() || renderer.needs_metadata() || options.shows_totals() || options.stat
+        || options.sort_keys.iter().any(|key| key.needs_metadata())
+        || matches!(options.grouping(), GroupBy::Owner | GroupBy::Age)
+}
+
+/// The entry of the directory, with its metadata read (relative to the
+/// directory) if needed. If there is a timeout for it, everything about
+/// the entry is looked up through the worker.
+fn read_entry(dir: &Dir, query: &Path, dentry: DirEntry, need_metadata: bool,
+              worker: &mut Option<StatWorker>) -> EnrichedEntry {
+    let Some(worker) = worker else {
+        return look_up_entry(dir, query, &dentry, need_metadata);
+    };
+    let name = dentry.name.clone();
+    let parent = query.to_path_buf();
+    worker.run(move |dir| look_up_entry(dir, &parent, &dentry, need_metadata))
+        .unwrap_or_else(|| EnrichedEntry::timed_out(query, &name))
+}
+
+fn look_up_entry(dir: &Dir, query: &Path, dentry: &DirEntry, need_metadata: bool) -> EnrichedEntry {
+    if need_metadata {
+        EnrichedEntry::in_dir(dir, query, &dentry.name)
+    } else {
+        EnrichedEntry::in_dir_without_metadata(dir, query, dentry)
     }
+}
 
-    pub fn get_icon(&self) -> String {
-        match self {
-            ListingEntry::Unknown { icon, .. } => { icon.to_string() }
-            ListingEntry::Regular { icon, .. } => { icon.to_string() }
-            ListingEntry::Directory { icon, .. } => { icon.to_string() }
-            ListingEntry::Symlink { icon, .. } => { icon.to_string() }
-            ListingEntry::Pipe { icon, .. } => { icon.to_string() }
-            ListingEntry::Socket { icon, .. } => { icon.to_string() }
-            ListingEntry::CharDevice { icon, .. } => { icon.to_string() }
-            ListingEntry::BlockDevice { icon, .. } => { icon.to_string() }
-        }
+fn stat_worker(dir: &Dir, options: &Options) -> Option<StatWorker> {
+    let timeout = Duration::from_millis(options.metadata_timeout? as u64);
+    StatWorker::new(dir, timeout).ok()
+}
+
+/// A directory the recursive listing is to go into.
+struct Subdir {
+    path: PathBuf,
+
+    /// reached through a symlink (`--follow-symlinks`)
+    followed: bool
+}
+
+impl Subdir {
+
+    /// Opens the subdirectory relative to the directory it is in.
+    fn open(&self, parent: &Dir) -> io::Result<Dir> {
+        let name = self.path.file_name().unwrap_or_default();
+        if self.followed { parent.open_following_at(name) } else { parent.open_at(name) }
     }
+}
+
+/// Prints the entries in the order the directory gives them, without
+/// collecting them first (and without fetching metadata unless
+/// the filters or the renderer need it). Returns the subdirectories found.
+fn stream_directory(out: &mut dyn Write, dir: &Dir, entries: Entries, query: &Path, renderer: &Renderer,
+                    options: &Options) -> io::Result<Vec<Subdir>> {
+    let need_metadata = needs_metadata(options, renderer);
+    let mut subdirs = Vec::new();
+    let mut worker = stat_worker(dir, options);
+    let mut progress = Progress::new(query);
+    let mut count = 0;
+    let entries = entries.inspect(|_| {
+        count += 1;
+        progress.tick(count);
+    });
 
-    pub fn is_directory(&self) -> bool {
-        match self {
-            ListingEntry::Directory { .. } => { true }
-            _ => { false }
+    // with a limit, the entries up to it are read first, so that a
+    // directory over it is given up on before any of it is printed, as
+    // when the entries are sorted
+    let entries: Box<dyn Iterator<Item = io::Result<DirEntry>>> = match options.max_entries {
+        Some(max) => {
+            let read: Vec<io::Result<DirEntry>> = entries.take(max.saturating_add(1)).collect();
+            if read.len() > max {
+                progress.finish();
+                report_too_many(query, max);
+                return Ok(Vec::new());
+            }
+            Box::new(read.into_iter())
+        }
+        None => { Box::new(entries) }
+    };
+    let mut found = entries
+        .map(|d| match d {
+            Ok(dentry) => { read_entry(dir, query, dentry, need_metadata, &mut worker) }
+            Err(err) => { EnrichedEntry::unreadable(&err) }
+        })
+        .inspect(|e| {
+            let _hidden = progress.hide();
+            report_entry_error(e, query, options)
+        })
+        .filter(|e| is_shown(e, options))
+        .map(|mut e| {
+            if let Some(subdir) = new_subdir(&mut e, options) {
+                subdirs.push(subdir);
+            }
+            e
+        });
+    let entries = dot_entries(query, options).into_iter()
+        .chain(found.by_ref())
+        .filter(|e| filter::matches_all(&options.filters, e))
+        .skip(options.window.offset)
+        .take(options.window.head.unwrap_or(usize::MAX));
+    let mut tally = Tally::default();
+    for e in entries {
+        let hidden = progress.hide();
+        renderer.print_entry(out, &e)?;
+        drop(hidden);
+        tally.add(&e);
+        if options.copy {
+            clipboard::add(&e.path);
         }
     }
 
-    pub fn new_regular(name: &str) -> ListingEntry {
-        ListingEntry::Regular {
-            name: name.to_string(),
-            icon: ICON_FILE.into()
-        }
+    // the window limits what is printed, not where the listing goes
+    found.for_each(drop);
+    progress.finish();
+    if options.shows_totals() {
+        tally.print_footer(out)?;
     }
+    Ok(subdirs)
+}
 
-    pub fn new_dir(name: &str) -> ListingEntry {
-        ListingEntry::Directory {
-            name: name.to_string(),
-            icon: ICON_DIRECTORY.into()
-        }
+/// The subdirectory if the recursive listing is to go into the entry: a
+/// directory, or with `--follow-symlinks` a symlink to one, that it has not
+/// been in yet. Those it has been in are marked as such.
+fn new_subdir(e: &mut EnrichedEntry, options: &Options) -> Option<Subdir> {
+    let subdir = |followed| Subdir { path: e.path.clone(), followed };
+    if !options.recursive {
+        return e.entry.is_directory().then(|| subdir(false));
+    }
+    let followed = options.follow_symlinks && matches!(e.entry.kind, EntryKind::Symlink { .. }) && e.path.is_dir();
+    if !e.entry.is_directory() && !followed {
+        return None;
     }
+    e.revisit = visited::revisit(&e.path);
+    e.revisit.is_none().then(|| subdir(followed))
+}
 
-    pub fn new_symlink(name: &str, target: &str) -> ListingEntry {
-        ListingEntry::Symlink {
-            name: name.to_string(),
-            target: target.to_string(),
-            icon: ICON_SYMLINK.into()
-        }
+/// Explains why a directory was not listed with `--max-entries`.
+fn report_too_many(query: &Path, max_entries: usize) {
+    eprintln!("{}", messages::fill("Directory '{}' has more than {} entries, giving up (see '--max-entries')",
+                                   &[&query.display(), &max_entries]));
+    HAD_ERRORS.store(true, Ordering::Relaxed);
+}
+
+/// With `--show-errors=stderr`, tells why the entry could not be read.
+fn report_entry_error(e: &EnrichedEntry, query: &Path, options: &Options) {
+    let Some(kind) = e.error.filter(|_| options.show_errors == ShowErrors::Stderr) else {
+        return;
+    };
+    if e.path.as_os_str().is_empty() {
+        eprintln!("{}", messages::fill("Could not read an entry of '{}': {}", &[&query.display(), &entry::error_reason(kind)]));
+    } else {
+        eprintln!("{}", messages::fill("Could not read '{}': {}", &[&e.path.display(), &entry::error_reason(kind)]));
     }
+    HAD_ERRORS.store(true, Ordering::Relaxed);
+}
+
+fn report_unopened(query: &Path, err: &io::Error) {
+    eprintln!("{}", messages::fill("Could not open '{}': {}", &[&query.display(), err]));
+    HAD_ERRORS.store(true, Ordering::Relaxed);
+}
 
-    pub fn new_unknown(name: &str) -> ListingEntry {
-        ListingEntry::Unknown {
-            name: name.to_string(),
-            icon: ICON_ERROR.into()
+/// Builds the list of the entries of the directory, with a progress
+/// indicator for large ones; `None` if there are too many (see `--max-entries`).
+fn read_listing(dir: &Dir, mut entries: Entries, query: &Path, need_metadata: bool,
+                options: &Options) -> Option<Vec<EnrichedEntry>> {
+    let mut listing = Vec::<EnrichedEntry>::new();
+    let mut progress = Progress::new(query);
+    let mut worker = stat_worker(dir, options);
+    while let Some(d) = timings::time(Phase::ReadDir, || entries.next()) {
+        if options.max_entries.is_some_and(|max| listing.len() >= max) {
+            progress.finish();
+            report_too_many(query, listing.len());
+            return None;
         }
-    }
+        progress.tick(listing.len());
+        match d {
+            Ok(dentry) if need_metadata => {
+                listing.push(timings::time(Phase::Metadata, || read_entry(dir, query, dentry, true, &mut worker)));
+            }
+            Ok(dentry) => { listing.push(read_entry(dir, query, dentry, false, &mut worker)) }
 
-    pub fn new_pipe(name: &str) -> ListingEntry {
-        ListingEntry::Pipe {
-            name: name.to_string(),
-            icon: ICON_PIPE.into()
+            // if the query fails, add at least the "???" entry
+            // to show that something was detected
+            Err(err) => { listing.push(EnrichedEntry::unreadable(&err)) }
         }
     }
+    progress.finish();
+    Some(listing)
+}
 
-    pub fn new_char_device(name: &str, dev_id: u64) -> ListingEntry {
-        let mut icon = ICON_CHAR_DEVICE;
+/// Reads the directory on a worker thread of `--threads`: like
+/// `read_listing()`, but without progress, timings or a metadata timeout,
+/// and stopping just past `limit` entries.
+fn read_ahead(path: &Path, dir: Dir, limit: usize) -> io::Result<Vec<EnrichedEntry>> {
+    Ok(dir.entries()?
+        .take(limit.saturating_add(1))
+        .map(|d| match d {
+            Ok(dentry) => { EnrichedEntry::in_dir(&dir, path, &dentry.name) }
+            Err(err) => { EnrichedEntry::unreadable(&err) }
+        })
+        .collect())
+}
 
-        // give some specific devices their own icons
-        let dev_major = (dev_id & 0x000000000000ff00) >> 8;
-        let dev_minor = dev_id & 0x00000000000000ff;
-        if dev_major == 1 && dev_minor == 3 {   // /dev/null
-            icon = ICON_DEV_NULL;
-        }
-        else if dev_major == 4 {                // oldschool ttys
-            icon = ICON_TTY;
+/// Lists a single directory. Returns its subdirectories in the order
+/// they were listed in, whether or not they passed the filters.
+fn list_directory(out: &mut dyn Write, query: &Path, dir: &Dir, options: &Options) -> io::Result<Vec<Subdir>> {
+    let mut renderer = Renderer::new(options, query);
+
+    // a worker may have read the directory already
+    let mut listing = match prefetch::take(query) {
+        Some(Ok(listing)) => {
+            if let Some(max) = options.max_entries.filter(|max| listing.len() > *max) {
+                report_too_many(query, max);
+                return Ok(Vec::new());
+            }
+            listing
         }
-        else if dev_major == 5 && (dev_minor == 0 || dev_minor == 1) {      // /dev/tty, /dev/console
-            icon = ICON_TTY;
+        Some(Err(err)) => {
+            report_unopened(query, &err);
+            return Ok(Vec::new());
         }
-        else if dev_major == 241 {              // disks
-            icon = ICON_DISK;
+        None => {
+
+            // open directory stream
+            let entries = match dir.entries() {
+                Ok(entries) => { entries }
+                Err(err) => {
+                    report_unopened(query, &err);
+                    return Ok(Vec::new());
+                }
+            };
+            if can_stream(options) {
+                return stream_directory(out, dir, entries, query, &renderer, options);
+            }
+            match read_listing(dir, entries, query, needs_metadata(options, &renderer), options) {
+                Some(listing) => { listing }
+                None => { return Ok(Vec::new()) }
+            }
         }
+    };
+
+    listing.iter().for_each(|e| report_entry_error(e, query, options));
+    listing.retain(|e| is_shown(e, options));
+    timings::time(Phase::Sort, || sort::sort_entries(&mut listing, &options.sort_keys, options.reverse, options.gnu));
+    let subdirs = listing.iter_mut()
+        .filter_map(|e| new_subdir(e, options))
+        .collect();
+
+    // "." and ".." come first whatever the order
+    let mut dots = dot_entries(query, options);
+    dots.append(&mut listing);
+    timings::time(Phase::Render, || print_listing(out, dots, &mut renderer, options.grouping(), options))?;
+    Ok(subdirs)
+}
 
-        ListingEntry::CharDevice {
-            name: name.to_string(),
-            dev_id: dev_id,
-            icon: icon.into()
-        }
+/// Filters the (already sorted) entries and prints what is left.
+fn print_listing(out: &mut dyn Write, mut listing: Vec<EnrichedEntry>, renderer: &mut Renderer,
+                 group_by: GroupBy, options: &Options) -> io::Result<()> {
+    listing.retain(|e| filter::matches_all(&options.filters, e));
+    if let Some(n) = options.sample {
+        random::sample(&mut listing, n);
     }
-
-    pub fn new_block_device(name: &str, dev_id: u64) -> ListingEntry {
-        ListingEntry::BlockDevice {
-            name: name.to_string(),
-            dev_id: dev_id,
-            icon: ICON_BLOCK_DEVICE.into()
-        }
+    if options.shuffle {
+        random::shuffle(&mut listing);
     }
 
-    pub fn new_socket(name: &str) -> ListingEntry {
-        ListingEntry::Socket {
-            name: name.to_string(),
-            icon: ICON_SOCKET.into()
-        }
+    // print the listing section by section; by default these are
+    // just the directories followed by other files, without headers
+    let groups = group::group_entries(&listing, group_by);
+    let groups = limit::apply_window(groups, &options.window);
+    let shown: Vec<&EnrichedEntry> = groups.iter().flat_map(|g| g.entries.iter().copied()).collect();
+    if options.copy {
+        shown.iter().for_each(|e| clipboard::add(&e.path));
     }
+    if options.stat {
+        return stats::Stats::collect(&shown).print(out, renderer.cell_format());
+    }
+    if let Some(command) = &options.exec {
 
-    pub fn from_dentry(dentry: &fs::DirEntry) -> ListingEntry {
-
-        // get the file name; this may fail, in which case
-        // we print "???" to at least show that there is something
-        let name = dentry.file_name().into_string();
-        if name.is_err() {
-            return ListingEntry::new_unknown("???");
+        // the command's output must come after ours
+        out.flush()?;
+        let success = if command.batch {
+            exec::exec_batch(&command.words, &shown)
+        } else {
+            exec::exec_each(&command.words, &shown)
+        };
+        if !success {
+            HAD_ERRORS.store(true, Ordering::Relaxed);
         }
-        let name = name.unwrap();
+        return Ok(());
+    }
+    renderer.prepare(&shown);
+    if ((options.long && options.gnu) || options.blocks) && !options.is_machine_output() {
+        renderer.print_total(out, &shown)?;
+    }
 
-        // identify file type; this can also fail, in which case
-        // we print the name and unknown type
-        let dentry_file_type = dentry.file_type();
-        if dentry_file_type.is_err() {
-            return ListingEntry::new_unknown(&name);
-        }
-        let dentry_file_type = dentry_file_type.unwrap();
+    let headers = group_by.has_headers() && !options.is_machine_output();
+    match options.grid(&System) {
 
-        if dentry_file_type.is_dir() {
-            ListingEntry::new_dir(&name)
+        // without headers, the groups only give the order, so share a grid
+        Some(direction) if !headers => {
+            let entries: Vec<&EnrichedEntry> = groups.iter().flat_map(|g| g.entries.iter().copied()).collect();
+            renderer.print_grid(out, &entries, direction)?;
         }
-        else if dentry_file_type.is_symlink() {
-            let result = fs::read_link(dentry.path());
-            match result {
-                Err(_) => { ListingEntry::new_symlink(&name, "???") }
-                Ok(target) => {
-                    match target.to_str() {
-                        Some(target) => {
-                            ListingEntry::new_symlink(&name, target)
-                        }
-                        None => {
-                            ListingEntry::new_symlink(&name, "???")
+        grid => {
+            for (i, g) in groups.iter().enumerate() {
+                if headers {
+                    if i > 0 { writeln!(out)?; }
+                    writeln!(out, "{} ({}):", g.title, g.entries.len())?;
+                }
+                match grid {
+                    Some(direction) => { renderer.print_grid(out, &g.entries, direction)? }
+                    None => {
+                        for l in &g.entries {
+                            renderer.print_entry(out, l)?;
                         }
                     }
                 }
             }
         }
-        else if dentry_file_type.is_fifo() {
-            ListingEntry::new_pipe(&name)
-        }
-        else if dentry_file_type.is_char_device() {
-            let result = dentry.metadata();
-            match result {
-                Err(_) => { ListingEntry::new_char_device(&name, 0) }
-                Ok(metadata) => {
-                    let dev_id = metadata.rdev();
-                    ListingEntry::new_char_device(&name, dev_id)
-                }
-            }
+    }
+    if options.shows_totals() {
+        let mut tally = Tally::default();
+        shown.iter().for_each(|e| tally.add(e));
+        tally.print_footer(out)?;
+    }
+    if options.summary == Some(Summary::Categories) && !options.is_machine_output() {
+        category::print_summary(out, &shown)?;
+    }
+    Ok(())
+}
+
+/// Reads paths, one per line, from the file (or stdin for "-").
+fn read_path_list(source: &str) -> io::Result<Vec<PathBuf>> {
+    let reader: Box<dyn BufRead> = if source == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(io::BufReader::new(fs::File::open(source)?))
+    };
+
+    // paths need not be valid UTF-8, so split the raw bytes
+    let mut paths = Vec::new();
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if !line.is_empty() {
+            paths.push(PathBuf::from(OsString::from_vec(line)));
         }
-        else if dentry_file_type.is_block_device() {
-            let result = dentry.metadata();
-            match result {
-                Err(_) => { ListingEntry::new_block_device(&name, 0) }
-                Ok(metadata) => {
-                    let dev_id = metadata.rdev();
-                    ListingEntry::new_block_device(&name, dev_id)
-                }
-            }
+    }
+    Ok(paths)
+}
+
+/// Lists the entries named in a file instead of those of a directory.
+fn list_files_from(out: &mut dyn Write, source: &str, options: &Options) -> io::Result<()> {
+    let paths = match read_path_list(source) {
+        Ok(paths) => { paths }
+        Err(err) => {
+            eprintln!("{}", messages::fill("Could not read '{}': {}", &[&source, &err]));
+            HAD_ERRORS.store(true, Ordering::Relaxed);
+            return Ok(());
         }
-        else if dentry_file_type.is_socket() {
-            ListingEntry::new_socket(&name)
+    };
+
+    list_paths(out, paths.iter().map(|p| EnrichedEntry::from_path(p)).collect(), options)
+}
+
+/// The entry for the operand itself (`-d`); for "fd:N", that of the
+/// directory the descriptor is open as.
+fn operand_entry(operand: &Operand) -> EnrichedEntry {
+    if operand.fd.is_none() {
+        return EnrichedEntry::from_path(Path::new(&operand.path));
+    }
+    match operand.open() {
+        Ok(dir) => { EnrichedEntry::of_dir(&operand.path, &dir) }
+        Err(err) => {
+            let unknown = EnrichedEntry::new_unknown(&operand.path);
+            EnrichedEntry { path: PathBuf::from(&operand.path), error: Some(err.kind()), ..unknown }
         }
-        else {
-            ListingEntry::new_regular(&name)
+    }
+}
+
+/// Lists the given entries themselves (`--files-from`, `-d`), rather
+/// than what is in them.
+fn list_paths(out: &mut dyn Write, mut listing: Vec<EnrichedEntry>, options: &Options) -> io::Result<()> {
+    sort::sort_entries(&mut listing, &options.sort_keys, options.reverse, options.gnu);
+    let mut renderer = Renderer::new(options, Path::new(""));
+    print_listing(out, listing, &mut renderer, options.grouping(), options)
+}
+
+/// Whether anything in the directory or below it would be listed, as
+/// `--prune-empty` asks; unreadable directories are kept, so that the
+/// error shows up.
+fn has_listed_entries(dir: &Dir, path: &Path, options: &Options) -> bool {
+    let Ok(entries) = dir.entries() else {
+        return true;
+    };
+    entries.flatten()
+        .map(|dentry| EnrichedEntry::in_dir(dir, path, &dentry.name))
+        .filter(|e| is_shown(e, options))
+        .any(|e| filter::matches_all(&options.filters, &e)
+            || (e.entry.is_directory() && dir.open_at(e.path.file_name().unwrap_or_default())
+                .map_or(true, |subdir| has_listed_entries(&subdir, &e.path, options))))
+}
+
+/// Measures the entries of the directory, and with `recursive` of those
+/// below it, for `--align global`.
+fn measure_tree(dir: &Dir, path: &Path, options: &Options, widths: &mut render::Widths) {
+    let Ok(entries) = dir.entries() else {
+        return;
+    };
+    let listing: Vec<EnrichedEntry> = dot_entries(path, options).into_iter()
+        .chain(entries.flatten().map(|dentry| EnrichedEntry::in_dir(dir, path, &dentry.name)).filter(|e| is_shown(e, options)))
+        .collect();
+    let shown: Vec<&EnrichedEntry> = listing.iter().filter(|e| filter::matches_all(&options.filters, e)).collect();
+    let mut renderer = Renderer::new(options, path);
+    renderer.prepare(&shown);
+    widths.merge(&renderer.measured());
+    if options.recursive {
+        for e in listing.iter().filter(|e| e.entry.is_directory()) {
+            if let Some(Ok(subdir)) = e.path.file_name().map(|name| dir.open_at(name)) {
+                measure_tree(&subdir, &e.path, options, widths);
+            }
         }
     }
 }
 
-impl PartialOrd for ListingEntry {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.get_name().partial_cmp(&other.get_name())
+/// Lists the directory (as opened, or why it could not be) and, if
+/// recursive, all directories below it, each opened relative to the one it
+/// is in. Each listing is introduced by its path when there is more than
+/// one (unless the entries are handed to a command instead of being printed).
+fn list_tree(out: &mut dyn Write, query: &Path, opened: io::Result<Dir>, options: &Options, first: &mut bool) -> io::Result<()> {
+    if options.prune_empty && opened.as_ref().is_ok_and(|dir| !has_listed_entries(dir, query, options)) {
+        prefetch::take(query);
+        return Ok(());
     }
+    let headers = options.exec.is_none() && !options.is_machine_output();
+    if (options.recursive || options.paths.len() > 1) && headers {
+        if !*first { writeln!(out)?; }
+        if options.dired && options.long {
+            write!(out, "  ")?;
+            dired::write_subdir(out, &query.display().to_string())?;
+            writeln!(out, ":")?;
+        } else {
+            writeln!(out, "{}:", query.display())?;
+        }
+    }
+    *first = false;
+    let dir = match opened {
+        Ok(dir) => { dir }
+        Err(err) => {
+            prefetch::take(query);
+            report_unopened(query, &err);
+            return Ok(());
+        }
+    };
+
+    visited::enter(&dir);
+    let subdirs = list_directory(out, query, &dir, options)?;
+    if options.recursive {
+        for subdir in &subdirs {
+            let Ok(opened) = subdir.open(&dir) else {
+                continue;
+            };
+            if !prefetch::request(&subdir.path, opened) {
+                break;
+            }
+        }
+        for subdir in subdirs {
+            list_tree(out, &subdir.path, subdir.open(&dir), options, first)?;
+        }
+    }
+    visited::leave();
+    Ok(())
 }
 
-impl Ord for ListingEntry {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.get_name().cmp(&other.get_name())
+/// Parses the command line, with the default options that the config file
+/// and then the local config file have for the (first) directory listed
+/// put before it.
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let options = Options::from_args(args)?;
+    let dir = PathBuf::from(&options.paths[0].path);
+    let program = args.iter().take(1).cloned();
+    let with_defaults = |defaults: &[String]| -> Vec<String> {
+        program.clone().chain(defaults.iter().cloned()).chain(args.iter().skip(1).cloned()).collect()
+    };
+
+    // the defaults are checked alone, so that their errors are told apart
+    let check = |defaults: &[String], source: &str| -> Result<(), String> {
+        let only_defaults: Vec<String> = program.clone().chain(defaults.iter().cloned()).collect();
+        Options::from_args(&only_defaults).map(|_| ()).map_err(|err| format!("{source}, {err}"))
+    };
+    let mut defaults = defaults::configured_args(&dir)?;
+    check(&defaults, "Config file")?;
+    let options = if defaults.is_empty() { options } else { Options::from_args(&with_defaults(&defaults))? };
+    if !options.local_config {
+        return Ok(options);
     }
+    let Some((path, local)) = defaults::local_args(&dir)? else {
+        return Ok(options);
+    };
+    check(&local, &format!("Local config file '{}'", path.display()))?;
+    defaults.extend(local);
+    Options::from_args(&with_defaults(&defaults))
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
-    // use the argument as the target dir; if none, use current dir
-    let mut query = ".";
-    if args.len() > 1 { query = &args[1]; }
-
-    // open directory stream
-    let rd = fs::read_dir(query).unwrap_or_else(|err| {
-        eprintln!("Could not open '{query}': {err}");
+    if let Err(err) = config::load() {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+    messages::set_language(Language::detect(&System));
+    let mut options = parse_options(&args).unwrap_or_else(|err| {
+        eprintln!("{err}");
         process::exit(1)
     });
+    options.paths = options.paths.iter()
+        .flat_map(|p| match p.fd {
+            Some(_) => { vec![p.clone()] }
+            None => { glob::expand_path(&p.path).iter().map(|path| Operand::path(path)).collect() }
+        })
+        .collect();
+    if options.no_lookup {
+        users::disable_lookup();
+    }
+    if options.timings {
+        timings::enable();
+    }
+    if options.recursive && options.threads > 1 && options.metadata_timeout.is_none() {
+        let limit = options.max_entries.unwrap_or(usize::MAX);
+        prefetch::start(options.threads, move |path, dir| read_ahead(path, dir, limit));
+    }
+    if options.gnu {
+        sys::use_locale_collation();
 
-    // build the list of files to show
-    let mut listing = Vec::<ListingEntry>::new();
-    for d in rd {
-        if let Ok(dentry) = d {
-            listing.push(ListingEntry::from_dentry(&dentry));
-        } else {
-
-            // if the query fails, add at least the "???" entry
-            // to show that something was detected
-            listing.push(ListingEntry::new_unknown("???"));
+        // GNU ls sorts the directories given as well
+        if !options.sort_keys.is_empty() {
+            options.paths.sort_by(|a, b| sys::collate(&a.path, &b.path));
+            if options.reverse { options.paths.reverse(); }
         }
     }
 
-    listing.sort();
-
-    // show directories first
-    for l in &listing {
-        if l.is_directory() {
-            println!("{} {}", l.get_icon(), l.get_name());
+    let writer: Box<dyn Write> = match &options.output_file {
+        Some(path) => {
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(options.append)
+                .truncate(!options.append)
+                .open(path);
+            match file {
+                Ok(file) => { Box::new(file) }
+                Err(err) => {
+                    eprintln!("{}", messages::fill("Could not open '{}' for writing: {}", &[path, &err]));
+                    process::exit(1)
+                }
+            }
         }
-    }
+        None => { Box::new(io::stdout().lock()) }
+    };
+    let mut out = dired::CountingWriter::new(BufWriter::new(writer));
+    emit::print_start(&mut out, options.output, &render::fields(&options))?;
 
-    // then other files
-    for l in &listing {
-        match l {
-            ListingEntry::Directory {..} => { },
-            ListingEntry::Symlink { name, icon, target } => {
-                println!("{} {} -> {}", icon, name, target);
+    if let Some(source) = &options.files_from {
+        list_files_from(&mut out, source, &options)?;
+    }
+    else if options.directory {
+        list_paths(&mut out, options.paths.iter().map(operand_entry).collect(), &options)?;
+    }
+    else {
+        if options.align == Align::Global {
+            let mut widths = render::Widths::default();
+            for query in &options.paths {
+                if let Ok(dir) = query.open() {
+                    measure_tree(&dir, Path::new(&query.path), &options, &mut widths);
+                }
+            }
+            render::set_global_widths(widths);
+        }
+        let mut first = true;
+        for query in &options.paths {
+            if options.is_report() && !first {
+                writeln!(out)?;
             }
-            _ => {
-                println!("{} {}", l.get_icon(), l.get_name());
+            if options.usage {
+                usage::print_usage(&mut out, query, &options)?;
+            } else if let Some(count) = options.largest {
+                largest::print_largest(&mut out, query, count, &options)?;
+            } else if options.dupes {
+                dupes::print_dupes(&mut out, query, &options)?;
+            } else {
+                list_tree(&mut out, Path::new(&query.path), query.open(), &options, &mut first)?;
             }
+            first = false;
         }
     }
 
+    if options.shows_totals() && options.files_from.is_none() && !options.directory {
+        writeln!(out)?;
+        totals::print_grand_total(&mut out)?;
+    }
+    emit::print_end(&mut out, options.output, &render::fields(&options))?;
+
+    // like GNU ls, dired mode only affects the long listing
+    if options.dired && options.long {
+        dired::print_trailer(&mut out)?;
+    }
+    out.flush()?;
+    if options.copy {
+        if let Err(err) = clipboard::copy() {
+            eprintln!("{}", messages::fill("Could not copy the paths to the clipboard: {}", &[&err]));
+            HAD_ERRORS.store(true, Ordering::Relaxed);
+        }
+    }
+    if options.timings {
+        timings::print_report();
+    }
+    if HAD_ERRORS.load(Ordering::Relaxed) || walk::had_errors() {
+        process::exit(1);
+    }
     Ok(())
 }
@@ -0,0 +1,192 @@
+//! The messages, headers and titles in the language of the user, picked
+//! by $LC_ALL, $LC_MESSAGES or $LANG ("cs_CZ.UTF-8" gives Czech); English
+//! for the languages without a catalog. The catalogs are built in, and
+//! look messages up by their English text, with "{}" where the arguments
+//! go. The forms of a count are separated by "|": in Czech, those of one,
+//! of two to four, and of more.
+//!
+//! The language is English until `set_language()` is called, so that the
+//! library (and its tests) give the same output everywhere.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::environment::Environment;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    English,
+    Czech
+}
+
+impl Language {
+
+    /// The language of the messages the environment asks for.
+    pub fn detect(env: &dyn Environment) -> Language {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| env.var(name))
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        match locale.split(['_', '.', '@']).next() {
+            Some("cs") => { Language::Czech }
+            _ => { Language::English }
+        }
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => { &[] }
+            Language::Czech => { &CZECH }
+        }
+    }
+
+    /// The message in this language.
+    pub fn tr(&self, english: &'static str) -> &'static str {
+        self.catalog().iter()
+            .find(|(key, _)| *key == english)
+            .map_or(english, |(_, translated)| translated)
+    }
+
+    /// The form of the message (forms separated by "|") for the count,
+    /// which also goes in the place of the "{}".
+    pub fn count(&self, english: &'static str, count: u64) -> String {
+        self.count_form(english, count).replace("{}", &count.to_string())
+    }
+
+    /// The form of the message for the count, with the "{}" left in.
+    pub fn count_form(&self, english: &'static str, count: u64) -> &'static str {
+        let forms: Vec<&'static str> = self.tr(english).split('|').collect();
+        let form = match (self, count) {
+            (_, 1) => { 0 }
+            (Language::Czech, 2..=4) => { 1 }
+            _ => { forms.len() - 1 }
+        };
+        forms[form.min(forms.len() - 1)]
+    }
+}
+
+const CZECH: [(&str, &str); 79] = [
+    ("total {}", "celkem {}"),
+    ("Total", "Celkem"),
+    ("{} file|{} files", "{} soubor|{} soubory|{} souborů"),
+    ("{} entry|{} entries", "{} položka|{} položky|{} položek"),
+    ("{} byte|{} bytes", "{} bajt|{} bajty|{} bajtů"),
+    ("{}, {} in files", "{}, {} v souborech"),
+    ("Total: {} in {}, {} in files", "Celkem: {} v {}, {} v souborech"),
+    ("{} directory|{} directories", "{} adresáři|{} adresářích|{} adresářích"),
+    ("Directories", "Adresáře"),
+    ("Symlinks", "Symbolické odkazy"),
+    ("Regular files", "Soubory"),
+    ("Pipes", "Roury"),
+    ("Sockets", "Sokety"),
+    ("Character devices", "Znaková zařízení"),
+    ("Block devices", "Bloková zařízení"),
+    ("Unknown", "Neznámé"),
+    ("Today", "Dnes"),
+    ("This week", "Tento týden"),
+    ("This month", "Tento měsíc"),
+    ("Older", "Starší"),
+    ("Unknown age", "Neznámé stáří"),
+    ("No extension", "Bez přípony"),
+    ("Other", "Ostatní"),
+    ("Code", "Kód"),
+    ("Documents", "Dokumenty"),
+    ("Images", "Obrázky"),
+    ("Audio", "Zvuk"),
+    ("Videos", "Videa"),
+    ("Archives", "Archivy"),
+    ("Configuration", "Konfigurace"),
+    ("just now", "právě teď"),
+    ("{} minute ago|{} minutes ago", "před {} minutou|před {} minutami|před {} minutami"),
+    ("{} hour ago|{} hours ago", "před {} hodinou|před {} hodinami|před {} hodinami"),
+    ("{} day ago|{} days ago", "před {} dnem|před {} dny|před {} dny"),
+    ("{} week ago|{} weeks ago", "před {} týdnem|před {} týdny|před {} týdny"),
+    ("in {} minute|in {} minutes", "za {} minutu|za {} minuty|za {} minut"),
+    ("in {} hour|in {} hours", "za {} hodinu|za {} hodiny|za {} hodin"),
+    ("in {} day|in {} days", "za {} den|za {} dny|za {} dní"),
+    ("in {} week|in {} weeks", "za {} týden|za {} týdny|za {} týdnů"),
+    (" (stat timed out)", " (vypršel čas pro stat)"),
+    (" (deleted during listing)", " (smazáno během výpisu)"),
+    (" (modified {})", " (změněno {})"),
+    (" [unusual owner]", " [neobvyklý vlastník]"),
+    (" [unusual permissions]", " [neobvyklá práva]"),
+    (" [unusual owner and permissions]", " [neobvyklý vlastník a práva]"),
+    ("access denied", "přístup odepřen"),
+    ("timed out", "vypršel čas"),
+    ("out of memory", "nedostatek paměti"),
+    ("I/O error", "chyba vstupu/výstupu"),
+    ("Could not open '{}': {}", "Nelze otevřít '{}': {}"),
+    ("Could not read '{}': {}", "Nelze číst '{}': {}"),
+    ("Could not read an entry of '{}': {}", "Nelze číst položku v '{}': {}"),
+    ("Could not open '{}' for writing: {}", "Nelze otevřít '{}' pro zápis: {}"),
+    ("Could not run '{}': {}", "Nelze spustit '{}': {}"),
+    ("Could not copy the paths to the clipboard: {}", "Nelze zkopírovat cesty do schránky: {}"),
+    ("Directory '{}' has more than {} entries, giving up (see '--max-entries')",
+        "Adresář '{}' má více než {} položek, vzdávám to (viz '--max-entries')"),
+    ("other", "ostatní"),
+    ("Entries:", "Položky:"),
+    ("Total size:", "Celková velikost:"),
+    ("Largest:", "Největší:"),
+    ("Newest:", "Nejnovější:"),
+    ("Extensions:", "Přípony:"),
+    ("(none)", "(žádná)"),
+    ("{}, {} each:", "{}, každý {}:"),
+    ("Reading '{}': {}", "Čtu '{}': {}"),
+    (" (loop)", " (smyčka)"),
+    (" (already listed)", " (již vypsáno)"),
+    (" (whiteout)", " (zakrytý soubor)"),
+    (" (broken)", " (nefunkční)"),
+    (" (unresolvable)", " (nelze vyhodnotit)"),
+    ("stream", "proudový"),
+    ("datagram", "datagramový"),
+    ("seqpacket", "paketový"),
+    ("unknown type", "neznámého typu"),
+    ("stale, nothing bound", "zastaralý, nic nenavázáno"),
+    ("listening, {}", "naslouchá, {}"),
+    ("bound, {}", "navázaný, {}"),
+    (", {} connected", ", připojených: {}"),
+    ("no such device here", "takové zařízení tu není")
+];
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+/// Makes the messages from now on be in the language.
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => { Language::Czech }
+        _ => { Language::English }
+    }
+}
+
+/// The message in the current language.
+pub fn tr(english: &'static str) -> &'static str {
+    language().tr(english)
+}
+
+/// The message in the current language, with the arguments in the place
+/// of the "{}"s, in order.
+pub fn fill(english: &'static str, args: &[&dyn Display]) -> String {
+    let mut parts = tr(english).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+/// The form of the message in the current language for the count.
+pub fn count(english: &'static str, count: u64) -> String {
+    language().count(english, count)
+}
+
+/// Like `count()`, but with the "{}" left in, for the caller to fill.
+pub fn count_form(english: &'static str, count: u64) -> &'static str {
+    language().count_form(english, count)
+}
@@ -0,0 +1,803 @@
+use std::io;
+use std::os::fd::RawFd;
+use std::path::Path;
+
+use crate::color::{ColorMode, Theme};
+use crate::columns::{self, Column};
+use crate::emit::Output;
+use crate::environment::Environment;
+use crate::exec;
+use crate::filter::Filter;
+use crate::format::{SizeUnit, TimeStyle};
+use crate::grid::Direction;
+use crate::group::{self, GroupBy};
+use crate::icons::IconMode;
+use crate::limit::Window;
+use crate::sort::{parse_sort_keys, SortKey};
+use crate::statfmt;
+use crate::sys::{self, Dir};
+
+/// How the names of the entries are displayed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathStyle {
+
+    /// just the name
+    Name,
+
+    /// prefixed with the directory as it was given
+    Full,
+
+    /// prefixed with the absolute, symlink-free path of the directory
+    Canonical
+}
+
+/// Everything that was requested on the command line.
+pub struct Options {
+
+    /// directories to list; "." if none were given
+    pub paths: Vec<Operand>,
+
+    /// keys to sort by, evaluated in order; empty for directory order
+    pub sort_keys: Vec<SortKey>,
+
+    /// reverse the sort order
+    pub reverse: bool,
+
+    /// how to split the listing into sections
+    pub group_by: GroupBy,
+
+    /// which part of the sorted listing to show
+    pub window: Window,
+
+    /// conditions that all listed entries must satisfy
+    pub filters: Vec<Filter>,
+
+    /// how to display the names
+    pub path_style: PathStyle,
+
+    /// show the paths of the entries relative to this directory instead
+    pub relative_to: Option<String>,
+
+    /// show the whole chain of symlinks pointing to symlinks
+    pub follow_chain: bool,
+
+    /// show metadata columns before the names
+    pub long: bool,
+
+    /// in the long listing, show the permissions also as an octal number
+    pub octal_permissions: bool,
+
+    /// print statistics instead of the entries
+    pub stat: bool,
+
+    /// descend into subdirectories
+    pub recursive: bool,
+
+    /// list the directories given, not what is in them
+    pub directory: bool,
+
+    /// in the recursive listing, go into symlinks to directories too
+    pub follow_symlinks: bool,
+
+    /// leave out the directories with nothing to list in their whole subtree
+    pub prune_empty: bool,
+
+    /// print the space used per extension instead of the entries
+    pub usage: bool,
+
+    /// print this many of the biggest files instead of the entries
+    pub largest: Option<usize>,
+
+    /// print groups of identical files instead of the entries
+    pub dupes: bool,
+
+    /// when to color the names
+    pub color: ColorMode,
+
+    /// which background to choose the colors for
+    pub theme: Theme,
+
+    /// when to show the icons
+    pub icons: IconMode,
+
+    /// dim the entries that belong to other users
+    pub dim_others: bool,
+
+    /// show owners and groups only where they are not the current ones
+    pub smart_owner: bool,
+
+    /// the line that sums up each listing, if any
+    pub summary: Option<Summary>,
+
+    /// list the paths read from this file ("-" for stdin) instead of directories
+    pub files_from: Option<String>,
+
+    /// instead of printing the entries, run this command for them
+    pub exec: Option<Exec>,
+
+    /// write the output to this file instead of stdout
+    pub output_file: Option<String>,
+
+    /// append to the output file instead of replacing it
+    pub append: bool,
+
+    /// mimic the output of GNU ls
+    pub gnu: bool,
+
+    /// print the offsets of the names for Emacs dired (implies `gnu`)
+    pub dired: bool,
+
+    /// show sizes as "1.5K" (the default, except in GNU mode)
+    pub human_sizes: bool,
+
+    /// show exact sizes, with the thousands separated
+    pub bytes: bool,
+
+    /// show the blocks allocated for each entry before it
+    pub blocks: bool,
+
+    /// the unit of all the sizes, or auto for each its own
+    pub size_unit: SizeUnit,
+
+    /// show a bar after each size, as long as the size compared to the largest
+    pub bar: bool,
+
+    /// how to write the times
+    pub time_style: TimeStyle,
+
+    /// show what symlinks point to in the long listing
+    pub link_target_metadata: bool,
+
+    /// show owners and groups by their ids, without looking up their names
+    pub no_lookup: bool,
+
+    /// point out entries whose owner or permissions differ from those of most
+    pub anomalies: bool,
+
+    /// show times as "2 days ago"
+    pub relative_time: bool,
+
+    /// which dot files to show
+    pub hidden: Hidden,
+
+    /// give up on directories with more entries than this, before any
+    /// of them is printed; it limits what is read, so the entries that
+    /// are not shown (e.g. dot files) count too
+    pub max_entries: Option<usize>,
+
+    /// how long to wait for the metadata of an entry, in milliseconds
+    pub metadata_timeout: Option<usize>,
+
+    /// what to do with entries that were deleted while being listed
+    pub stale: Stale,
+
+    /// where to tell why entries could not be read
+    pub show_errors: ShowErrors,
+
+    /// table, CSV or JSON
+    pub output: Output,
+
+    /// the fields to show, if not the usual ones
+    pub fields: Option<Vec<Column>>,
+
+    /// escape the names and paths in the machine-readable outputs, so that
+    /// they can be told back exactly, and add the "escaped" field
+    pub escape_names: bool,
+
+    /// the usual fields to leave out (`-g`, `-o`)
+    pub omitted_fields: Vec<Column>,
+
+    /// mark directories that are project roots (looking inside each of
+    /// them, so only when asked for)
+    pub badges: bool,
+
+    /// print each entry in this format, as `stat --format` does
+    pub stat_format: Option<Vec<statfmt::Piece>>,
+
+    /// list the entries in random order
+    pub shuffle: bool,
+
+    /// list only this many randomly chosen entries
+    pub sample: Option<usize>,
+
+    /// put a slash after the names of directories
+    pub dir_slash: bool,
+
+    /// shorten longer names to this many characters
+    pub max_name_width: Option<usize>,
+
+    /// show the filesystem each entry is on
+    pub filesystems: bool,
+
+    /// show whether anything listens on the sockets
+    pub socket_info: bool,
+
+    /// show when pipes and sockets were last modified, even outside the long listing
+    pub ipc_age: bool,
+
+    /// show where the files in a trash were deleted from, and when
+    pub trash_info: bool,
+
+    /// hide backups and the like
+    pub ignore_backups: bool,
+
+    /// one name per line or a grid; if not given, a grid on terminals
+    pub layout: Option<Layout>,
+
+    /// how wide the grid may get; 0 for no limit
+    pub width: Option<usize>,
+
+    /// whether the columns line up within each directory, or across all
+    pub align: Align,
+
+    /// put the paths of the listed entries on the clipboard
+    pub copy: bool,
+
+    /// take options from the .my_ls.toml of the listed directory
+    pub local_config: bool,
+
+    /// tell on stderr how each entry was recognized
+    pub debug: bool,
+
+    /// tell on stderr how long each phase of the listing took
+    pub timings: bool,
+
+    /// how many threads read directories ahead of the recursive listing
+    pub threads: usize
+}
+
+/// Over which entries the columns of the long listing line up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Align {
+
+    /// those of the same directory
+    Directory,
+
+    /// all that are listed
+    Global
+}
+
+impl Align {
+
+    pub fn parse(s: &str) -> Option<Align> {
+        match s {
+            "directory" => { Some(Align::Directory) }
+            "global" => { Some(Align::Global) }
+            _ => { None }
+        }
+    }
+}
+
+/// How the names are laid out outside the long listing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layout {
+
+    /// one per line (`-1`)
+    Lines,
+
+    /// in columns filled in the given order (`-C`, `-x`)
+    Grid(Direction)
+}
+
+/// Which entries whose names start with a dot are shown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hidden {
+    Hide,
+
+    /// all of them, except "." and ".." (`-A`)
+    AlmostAll,
+
+    /// all of them, including "." and ".." (`-a`)
+    All
+}
+
+/// What becomes of entries that disappear between reading the directory
+/// and reading their metadata.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stale {
+
+    /// leave them out
+    Skip,
+
+    /// list them, marked as deleted
+    Mark
+}
+
+impl Stale {
+
+    pub fn parse(s: &str) -> Option<Stale> {
+        match s {
+            "skip" => { Some(Stale::Skip) }
+            "mark" => { Some(Stale::Mark) }
+            _ => { None }
+        }
+    }
+}
+
+/// Where the reasons go why entries could not be read (`--show-errors`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShowErrors {
+
+    /// next to the entries, dimmed
+    Inline,
+    Stderr,
+    None
+}
+
+impl ShowErrors {
+
+    pub fn parse(s: &str) -> Option<ShowErrors> {
+        match s {
+            "inline" => { Some(ShowErrors::Inline) }
+            "stderr" => { Some(ShowErrors::Stderr) }
+            "none" => { Some(ShowErrors::None) }
+            _ => { None }
+        }
+    }
+}
+
+/// What is summed up after each listing (`--summary`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Summary {
+
+    /// the number and size of the entries of each category
+    Categories
+}
+
+impl Summary {
+
+    pub fn parse(s: &str) -> Option<Summary> {
+        match s {
+            "categories" => { Some(Summary::Categories) }
+            _ => { None }
+        }
+    }
+}
+
+/// A command to run for the listed entries.
+pub struct Exec {
+
+    /// the command split into words, possibly containing "{}"
+    pub words: Vec<String>,
+
+    /// run the command once with all the paths, rather than once per entry
+    pub batch: bool
+}
+
+fn parse_count(name: &str, value: &str) -> Result<usize, String> {
+    value.parse::<usize>().map_err(|_| format!("Option '--{name}' requires a number, not '{value}'"))
+}
+
+/// A directory to list, as given on the command line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Operand {
+
+    /// what the listing shows for it: the path, or "fd:N"
+    pub path: String,
+
+    /// the descriptor the directory is open as, for "fd:N"
+    pub fd: Option<RawFd>
+}
+
+impl Operand {
+
+    pub fn path(path: &str) -> Operand {
+        Operand { path: path.to_string(), fd: None }
+    }
+
+    /// The operand as given: "fd:N" stands for the directory open as
+    /// descriptor N (inherited e.g. from a sandbox that does not let us
+    /// open it by path). After "--", operands are always paths, so that
+    /// "fd:3" can be a name.
+    fn parse(arg: &str) -> Result<Operand, String> {
+        match arg.strip_prefix("fd:") {
+            Some(fd) => {
+                let fd: RawFd = fd.parse().ok().filter(|fd| *fd >= 0)
+                    .ok_or_else(|| format!("Invalid file descriptor '{arg}'"))?;
+                Ok(Operand { path: arg.to_string(), fd: Some(fd) })
+            }
+            None => { Ok(Operand::path(arg)) }
+        }
+    }
+
+    /// Opens the directory; the descriptor of "fd:N" is duplicated
+    /// rather than the directory reopened.
+    pub fn open(&self) -> io::Result<Dir> {
+        match self.fd {
+            Some(fd) => { Dir::inherited(fd) }
+            None => { Dir::open(Path::new(&self.path)) }
+        }
+    }
+}
+
+impl Options {
+
+    /// Parses the command line arguments (including the program name).
+    pub fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            paths: Vec::new(),
+            sort_keys: vec![SortKey::Name],
+            reverse: false,
+            group_by: GroupBy::DirsFirst,
+            window: Window::default(),
+            filters: Vec::new(),
+            path_style: PathStyle::Name,
+            relative_to: None,
+            follow_chain: false,
+            long: false,
+            octal_permissions: false,
+            stat: false,
+            recursive: false,
+            directory: false,
+            follow_symlinks: false,
+            prune_empty: false,
+            usage: false,
+            largest: None,
+            dupes: false,
+            color: ColorMode::Auto,
+            theme: Theme::Auto,
+            icons: IconMode::Auto,
+            dim_others: false,
+            smart_owner: false,
+            summary: None,
+            files_from: None,
+            exec: None,
+            output_file: None,
+            append: false,
+            gnu: false,
+            dired: false,
+            human_sizes: false,
+            bytes: false,
+            blocks: false,
+            size_unit: SizeUnit::Auto,
+            bar: false,
+            time_style: TimeStyle::Default,
+            link_target_metadata: false,
+            no_lookup: false,
+            anomalies: false,
+            relative_time: false,
+            hidden: Hidden::Hide,
+            max_entries: None,
+            metadata_timeout: None,
+            stale: Stale::Mark,
+            show_errors: ShowErrors::Inline,
+            output: Output::Table,
+            fields: None,
+            escape_names: false,
+            omitted_fields: Vec::new(),
+            badges: false,
+            stat_format: None,
+            shuffle: false,
+            sample: None,
+            dir_slash: false,
+            max_name_width: None,
+            filesystems: false,
+            socket_info: false,
+            ipc_age: false,
+            trash_info: false,
+            ignore_backups: false,
+            layout: None,
+            width: None,
+            align: Align::Directory,
+            copy: false,
+            local_config: true,
+            debug: false,
+            timings: false,
+            threads: 1
+        };
+
+        let mut only_paths = false;
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            if only_paths {
+                options.paths.push(Operand::path(arg));
+            }
+            else if arg == "-" || !arg.starts_with('-') {
+                options.paths.push(Operand::parse(arg)?);
+            }
+            else if arg == "--" {
+                only_paths = true;
+            }
+            else if let Some(long) = arg.strip_prefix("--") {
+
+                // the value can be given as "--name=value" or "--name value"
+                let (name, inline_value) = match long.split_once('=') {
+                    Some((name, value)) => { (name, Some(value.to_string())) }
+                    None => { (long, None) }
+                };
+                let mut value = || -> Result<String, String> {
+                    match inline_value.clone().or_else(|| iter.next().cloned()) {
+                        Some(value) => { Ok(value) }
+                        None => { Err(format!("Option '--{name}' requires a value")) }
+                    }
+                };
+
+                match name {
+                    "sort" => { options.sort_keys = parse_sort_keys(&value()?)? }
+                    "reverse" => { options.reverse = true }
+                    "group-by" => {
+                        let value = value()?;
+                        options.group_by = GroupBy::parse(&value)
+                            .ok_or_else(|| format!("Unknown grouping '{value}'"))?;
+                    }
+                    "no-dirs-first" => { options.group_by = GroupBy::None }
+                    "head" | "limit" => { options.window.head = Some(parse_count(name, &value()?)?) }
+                    "tail" => { options.window.tail = Some(parse_count(name, &value()?)?) }
+                    "offset" => { options.window.offset = parse_count(name, &value()?)? }
+                    "owner" => { options.filters.push(Filter::parse_owner(&value()?)?) }
+                    "group" => { options.filters.push(Filter::parse_group(&value()?)?) }
+                    "only-mine" => { options.filters.push(Filter::Owner(sys::effective_uid())) }
+                    "not-mine" => { options.filters.push(Filter::NotOwner(sys::effective_uid())) }
+                    "perm" => { options.filters.push(Filter::parse_perm(&value()?)?) }
+                    "only-broken-symlinks" => { options.filters.push(Filter::BrokenSymlink) }
+                    "only-empty" => { options.filters.push(Filter::Empty) }
+                    "category" => { options.filters.push(Filter::parse_category(&value()?)?) }
+                    "full-path" => { options.path_style = PathStyle::Full }
+                    "canonical" => { options.path_style = PathStyle::Canonical }
+                    "relative-to" => { options.relative_to = Some(value()?) }
+                    "follow-chain" => { options.follow_chain = true }
+                    "long" => { options.long = true }
+                    "octal-permissions" => { options.octal_permissions = true }
+                    "stat" => { options.stat = true }
+                    "recursive" => { options.recursive = true }
+                    "directory" => { options.directory = true }
+                    "follow-symlinks" => { options.follow_symlinks = true }
+                    "prune-empty" => { options.prune_empty = true }
+                    "usage" => { options.usage = true }
+                    "largest" => { options.largest = Some(parse_count(name, &value()?)?) }
+                    "dupes" => { options.dupes = true }
+                    "color" | "colour" => {
+                        let value = value()?;
+                        options.color = ColorMode::parse(&value)
+                            .ok_or_else(|| format!("Invalid color mode '{value}'"))?;
+                    }
+                    "icons" => {
+                        let value = value()?;
+                        options.icons = IconMode::parse(&value)
+                            .ok_or_else(|| format!("Invalid icon mode '{value}'"))?;
+                    }
+                    "no-icons" => { options.icons = IconMode::Never }
+                    "files-from" => { options.files_from = Some(value()?) }
+                    "exec" | "exec-batch" => {
+                        let words = exec::split_command(&value()?)?;
+                        options.exec = Some(Exec { words, batch: name == "exec-batch" });
+                    }
+                    "output-file" => { options.output_file = Some(value()?) }
+                    "append" => { options.append = true }
+                    "gnu" => { options.gnu = true }
+                    "dired" => { options.dired = true }
+                    "human-readable" => { options.human_sizes = true }
+                    "bytes" => { options.bytes = true }
+                    "size" => { options.blocks = true }
+                    "bar" => { options.bar = true }
+                    "link-target-metadata" => { options.link_target_metadata = true }
+                    "no-lookup" => { options.no_lookup = true }
+                    "anomalies" => { options.anomalies = true }
+                    "time-style" => {
+                        let value = value()?;
+                        options.time_style = TimeStyle::parse(&value)
+                            .ok_or_else(|| format!("Invalid value '{value}' for '--time-style'"))?;
+                    }
+                    "size-unit" => {
+                        let value = value()?;
+                        options.size_unit = SizeUnit::parse(&value)
+                            .ok_or_else(|| format!("Invalid value '{value}' for '--size-unit'"))?;
+                    }
+                    "relative-time" => { options.relative_time = true }
+                    "output" => {
+                        let value = value()?;
+                        options.output = Output::parse(&value)
+                            .ok_or_else(|| format!("Unknown output format '{value}'"))?;
+                    }
+                    "porcelain" => {
+
+                        // the version is optional, so only "v1" or "v2" is
+                        // taken for it from the next argument ("./v2" lists
+                        // a directory of that name)
+                        let next_version = iter.clone().next().filter(|next| *next == "v1" || *next == "v2");
+                        let version = inline_value.clone().or_else(|| next_version.and_then(|_| iter.next().cloned()));
+                        match version.as_deref() {
+                            None | Some("v1") => { options.output = Output::Porcelain(1) }
+                            Some("v2") => { options.output = Output::Porcelain(2) }
+                            Some(version) => { return Err(format!("Unknown porcelain version '{version}'")) }
+                        }
+                    }
+                    "fields" => { options.fields = Some(columns::parse_fields(&value()?)?) }
+                    "escape-names" => { options.escape_names = true }
+                    "debug" => { options.debug = true }
+                    "copy" => { options.copy = true }
+                    "no-local-config" => { options.local_config = false }
+                    "timings" => { options.timings = true }
+                    "threads" => { options.threads = parse_count(name, &value()?)? }
+                    "align" => {
+                        let value = value()?;
+                        options.align = Align::parse(&value)
+                            .ok_or_else(|| format!("Unknown alignment '{value}'"))?;
+                    }
+                    "width" => { options.width = Some(parse_count(name, &value()?)?) }
+                    "fs" => { options.filesystems = true }
+                    "socket-info" => { options.socket_info = true }
+                    "ipc-age" => { options.ipc_age = true }
+                    "trash-info" => { options.trash_info = true }
+                    "ignore-backups" => { options.ignore_backups = true }
+                    "dir-slash" => { options.dir_slash = true }
+                    "max-name-width" => { options.max_name_width = Some(parse_count(name, &value()?)?) }
+                    "shuffle" => { options.shuffle = true }
+                    "sample" => { options.sample = Some(parse_count(name, &value()?)?) }
+                    "stat-format" => { options.stat_format = Some(statfmt::parse(&value()?)?) }
+                    "badges" => { options.badges = true }
+                    "no-badges" => { options.badges = false }
+                    "metadata-timeout" => { options.metadata_timeout = Some(parse_count(name, &value()?)?) }
+                    "stale" => {
+                        let value = value()?;
+                        options.stale = Stale::parse(&value)
+                            .ok_or_else(|| format!("Invalid value '{value}' for '--stale'"))?;
+                    }
+                    "show-errors" => {
+                        let value = value()?;
+                        options.show_errors = ShowErrors::parse(&value)
+                            .ok_or_else(|| format!("Invalid value '{value}' for '--show-errors'"))?;
+                    }
+                    "max-entries" => { options.max_entries = Some(parse_count(name, &value()?)?) }
+                    "all" => { options.hidden = Hidden::All }
+                    "almost-all" => { options.hidden = Hidden::AlmostAll }
+                    "dim-others" => { options.dim_others = true }
+                    "smart-owner" => { options.smart_owner = true }
+                    "summary" => {
+                        let value = value()?;
+                        options.summary = Some(Summary::parse(&value)
+                            .ok_or_else(|| format!("Unknown summary '{value}'"))?);
+                    }
+                    "theme" => {
+                        let value = value()?;
+                        options.theme = Theme::parse(&value)
+                            .ok_or_else(|| format!("Unknown theme '{value}'"))?;
+                    }
+                    _ => { return Err(format!("Unknown option '--{name}'")) }
+                }
+            }
+            else {
+                for c in arg.chars().skip(1) {
+                    match c {
+                        'S' => { options.sort_keys = vec![SortKey::Size] }
+                        't' => { options.sort_keys = vec![SortKey::Time] }
+                        'X' => { options.sort_keys = vec![SortKey::Extension] }
+                        'U' => { options.sort_keys = Vec::new() }
+                        'r' => { options.reverse = true }
+                        'l' => { options.long = true }
+                        'g' => {
+                            options.long = true;
+                            options.omitted_fields.push(Column::Owner);
+                        }
+                        'o' => {
+                            options.long = true;
+                            options.omitted_fields.push(Column::Group);
+                        }
+                        'R' => { options.recursive = true }
+                        'd' => { options.directory = true }
+                        'p' => { options.dir_slash = true }
+                        'h' => { options.human_sizes = true }
+                        's' => { options.blocks = true }
+                        'a' => { options.hidden = Hidden::All }
+                        'A' => { options.hidden = Hidden::AlmostAll }
+                        'B' => { options.ignore_backups = true }
+                        '1' => { options.layout = Some(Layout::Lines) }
+                        'C' => { options.layout = Some(Layout::Grid(Direction::Down)) }
+                        'x' => { options.layout = Some(Layout::Grid(Direction::Across)) }
+                        _ => { return Err(format!("Unknown option '-{c}'")) }
+                    }
+                }
+            }
+        }
+
+        if options.paths.is_empty() {
+            options.paths.push(Operand::path("."));
+        }
+        if options.append && options.output_file.is_none() {
+            return Err("Option '--append' requires '--output-file'".to_string());
+        }
+
+        // the reports and the commands print what they print, which no
+        // output format can wrap
+        if options.output != Output::Table {
+            let exec = options.exec.as_ref().map(|exec| if exec.batch { "--exec-batch" } else { "--exec" });
+            let modes = [
+                options.usage.then_some("--usage"),
+                options.largest.map(|_| "--largest"),
+                options.dupes.then_some("--dupes"),
+                options.stat.then_some("--stat"),
+                exec
+            ];
+            if let Some(mode) = modes.into_iter().flatten().next() {
+                return Err(format!("Option '--output' cannot be used with '{mode}'"));
+            }
+        }
+
+        // picking the fields of the table only makes sense for the long listing
+        if options.fields.is_some() && !options.is_machine_output() {
+            options.long = true;
+        }
+
+        // as in ls, listing the directories themselves wins over going into them
+        if options.directory {
+            options.recursive = false;
+        }
+
+        // dired parses the output as that of GNU ls
+        if options.dired {
+            options.gnu = true;
+        }
+
+        // GNU ls mixes directories with files, and has no colors by default
+        if options.gnu {
+            if options.group_by == GroupBy::DirsFirst {
+                options.group_by = GroupBy::None;
+            }
+            if options.color == ColorMode::Auto {
+                options.color = ColorMode::Never;
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether the output is meant for programs rather than humans, so
+    /// that it has no headers, totals and such.
+    pub fn is_machine_output(&self) -> bool {
+        self.output.is_machine() || self.stat_format.is_some()
+    }
+
+    /// How the listing is split into sections, with the directories
+    /// placed as configured for this kind of listing.
+    pub fn grouping(&self) -> GroupBy {
+        let mode = if self.recursive {
+            group::Mode::Tree
+        } else if self.long {
+            group::Mode::Long
+        } else {
+            group::Mode::Grid
+        };
+        group::for_mode(self.group_by, mode)
+    }
+
+    /// Whether the recursive listing has footers with the number and
+    /// size of the entries (not in GNU mode, nor when they would be in the way).
+    pub fn shows_totals(&self) -> bool {
+        self.recursive && !self.gnu && !self.is_machine_output()
+            && self.exec.is_none() && !self.stat && !self.is_report()
+    }
+
+    /// Whether a report on the trees (`--usage`, `--largest`, `--dupes`)
+    /// is printed instead of the entries.
+    pub fn is_report(&self) -> bool {
+        self.usage || self.largest.is_some() || self.dupes
+    }
+
+    /// The order to fill the grid in, if the names are laid out in one:
+    /// by default on terminals, and never when each entry takes a line.
+    pub fn grid(&self, env: &dyn Environment) -> Option<Direction> {
+        if self.long || self.is_machine_output() || self.exec.is_some() || self.stat {
+            return None;
+        }
+        match self.layout {
+            Some(Layout::Grid(direction)) => { Some(direction) }
+            Some(Layout::Lines) => { None }
+            None => { self.output_is_terminal(env).then_some(Direction::Down) }
+        }
+    }
+
+    /// How wide the lines of the grid may get: as given by `--width`, or
+    /// the width of the terminal, or $COLUMNS when not on one, or 80;
+    /// None if there is no limit (a width of 0).
+    pub fn line_length(&self, env: &dyn Environment) -> Option<usize> {
+        let width = self.width
+            .or_else(|| if self.output_is_terminal(env) { env.terminal_width() } else { None })
+            .or_else(|| env.var("COLUMNS").and_then(|c| c.parse().ok()))
+            .unwrap_or(80);
+        (width > 0).then_some(width)
+    }
+
+    /// Whether the output goes to a terminal (rather than to a pipe or file).
+    pub fn output_is_terminal(&self, env: &dyn Environment) -> bool {
+        self.output_file.is_none() && env.is_tty()
+    }
+}
@@ -0,0 +1,120 @@
+//! Reading directories ahead of the recursive listing (`--threads`).
+//! Worker threads read the subdirectories that are to be listed next,
+//! while the listing prints the directories one by one, in its usual
+//! order. Only a few directories per thread are read ahead, so memory
+//! stays flat however large the tree is.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+
+use crate::entry::EnrichedEntry;
+use crate::sys::Dir;
+
+/// The entries of a directory, or why it could not be read.
+pub type Listing = io::Result<Vec<EnrichedEntry>>;
+
+/// Reads the directory at the path, open as the `Dir`.
+type Reader = dyn Fn(&Path, Dir) -> Listing + Send + Sync;
+
+/// How many directories may be read ahead per thread.
+const AHEAD_PER_THREAD: usize = 4;
+
+#[derive(Default)]
+struct State {
+
+    /// requested and not yet taken, whether read or not
+    requested: HashSet<PathBuf>,
+
+    /// waiting for a worker, already open
+    queue: VecDeque<(PathBuf, Dir)>,
+
+    /// read and waiting to be taken
+    done: HashMap<PathBuf, Listing>
+}
+
+struct Pool {
+    state: Mutex<State>,
+
+    /// signalled when a directory is queued
+    queued: Condvar,
+
+    /// signalled when a directory has been read
+    read: Condvar,
+
+    capacity: usize,
+    reader: Box<Reader>
+}
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+impl Pool {
+
+    fn work(&self) {
+        loop {
+            let (path, dir) = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    match state.queue.pop_front() {
+                        Some(request) => { break request }
+                        None => { state = self.queued.wait(state).unwrap() }
+                    }
+                }
+            };
+            let listing = (self.reader)(&path, dir);
+            self.state.lock().unwrap().done.insert(path, listing);
+            self.read.notify_all();
+        }
+    }
+}
+
+/// Starts the worker threads, which read directories with `reader`.
+pub fn start(threads: usize, reader: impl Fn(&Path, Dir) -> Listing + Send + Sync + 'static) {
+    let pool = Pool {
+        state: Mutex::new(State::default()),
+        queued: Condvar::new(),
+        read: Condvar::new(),
+        capacity: threads * AHEAD_PER_THREAD,
+        reader: Box::new(reader)
+    };
+    if POOL.set(pool).is_err() {
+        return;
+    }
+    for _ in 0..threads {
+        thread::spawn(|| POOL.get().unwrap().work());
+    }
+}
+
+/// Asks for the directory, open as `dir`, to be read ahead. Returns false
+/// if the workers are not running, or have enough directories to read or
+/// to hand over.
+pub fn request(path: &Path, dir: Dir) -> bool {
+    let Some(pool) = POOL.get() else {
+        return false;
+    };
+    let mut state = pool.state.lock().unwrap();
+    if state.requested.len() >= pool.capacity || !state.requested.insert(path.to_path_buf()) {
+        return false;
+    }
+    state.queue.push_back((path.to_path_buf(), dir));
+    pool.queued.notify_one();
+    true
+}
+
+/// The listing of the directory, waiting for it to be read; `None` if it
+/// was not requested.
+pub fn take(dir: &Path) -> Option<Listing> {
+    let pool = POOL.get()?;
+    let mut state = pool.state.lock().unwrap();
+    if !state.requested.remove(dir) {
+        return None;
+    }
+    loop {
+        if let Some(listing) = state.done.remove(dir) {
+            return Some(listing);
+        }
+        state = pool.read.wait(state).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+//! A spinner on stderr for directories that take long to read.
+
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::messages;
+
+/// Nothing is shown for directories read faster than this.
+const DELAY: Duration = Duration::from_millis(200);
+
+/// How often the spinner is redrawn.
+const REDRAW: Duration = Duration::from_millis(100);
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// What the spinner and the listing share.
+#[derive(Default)]
+struct State {
+    count: AtomicUsize,
+
+    /// whether the spinner is on the screen
+    drawn: Mutex<bool>
+}
+
+/// Shows how many entries were read so far, once reading takes a while.
+/// The spinner is redrawn on a timer, so that it keeps turning while a
+/// single read hangs.
+pub struct Progress {
+    state: Arc<State>,
+
+    /// stops the timer when dropped; `None` if stderr is not a terminal,
+    /// where a spinner would be garbage
+    stop: Option<Sender<()>>,
+    timer: Option<JoinHandle<()>>
+}
+
+impl Progress {
+
+    pub fn new(path: &Path) -> Progress {
+        let state = Arc::new(State::default());
+        if !io::stderr().is_terminal() {
+            return Progress { state, stop: None, timer: None };
+        }
+        let (stop, stopped) = mpsc::channel::<()>();
+        let path = path.display().to_string();
+        let shared = state.clone();
+        let timer = thread::spawn(move || {
+            let mut wait = DELAY;
+            let mut frame = 0;
+            while stopped.recv_timeout(wait) == Err(RecvTimeoutError::Timeout) {
+                frame = (frame + 1) % FRAMES.len();
+                let entries = messages::count("{} entry|{} entries", shared.count.load(Ordering::Relaxed) as u64);
+                let mut drawn = shared.drawn.lock().unwrap_or_else(|e| e.into_inner());
+                eprint!("\r\x1b[K{} {}", FRAMES[frame], messages::fill("Reading '{}': {}", &[&path, &entries]));
+                let _ = io::stderr().flush();
+                *drawn = true;
+                wait = REDRAW;
+            }
+        });
+        Progress { state, stop: Some(stop), timer: Some(timer) }
+    }
+
+    /// Called for every entry read; `count` is the number read so far.
+    pub fn tick(&self, count: usize) {
+        self.state.count.store(count, Ordering::Relaxed);
+    }
+
+    /// Takes the spinner off the screen until the guard is dropped, for
+    /// printing to the terminal meanwhile.
+    pub fn hide(&self) -> MutexGuard<'_, bool> {
+        let mut drawn = self.state.drawn.lock().unwrap_or_else(|e| e.into_inner());
+        if *drawn {
+            eprint!("\r\x1b[K");
+            let _ = io::stderr().flush();
+            *drawn = false;
+        }
+        drawn
+    }
+
+    /// Stops the spinner and removes it, if it was shown.
+    pub fn finish(&mut self) {
+        self.stop = None;
+        if let Some(timer) = self.timer.take() {
+            let _ = timer.join();
+        }
+        drop(self.hide());
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,60 @@
+//! Random order for `--shuffle` and `--sample`; std has no random
+//! numbers, but its hash maps are seeded randomly, which we can use.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// The xorshift64* generator, good enough for shuffling.
+struct Rng(u64);
+
+impl Rng {
+
+    fn new() -> Rng {
+        let seed = RandomState::new().build_hasher().finish();
+
+        // the state must not be zero
+        Rng(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number from 0 to `bound - 1`; the bias is negligible for
+    /// the sizes of directories.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Shuffles the first `n` items (Fisher-Yates), so that they are a random
+/// sample of all of them in random order.
+fn shuffle_prefix<T>(items: &mut [T], n: usize, rng: &mut Rng) {
+    for i in 0..n.min(items.len().saturating_sub(1)) {
+        let j = i + rng.below(items.len() - i);
+        items.swap(i, j);
+    }
+}
+
+/// Puts the items in random order.
+pub fn shuffle<T>(items: &mut [T]) {
+    shuffle_prefix(items, items.len(), &mut Rng::new());
+}
+
+/// Keeps `n` randomly chosen items, in the order they were in.
+pub fn sample<T>(items: &mut Vec<T>, n: usize) {
+    if n >= items.len() {
+        return;
+    }
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    shuffle_prefix(&mut indices, n, &mut Rng::new());
+    let mut keep = vec![false; items.len()];
+    for i in &indices[..n] {
+        keep[*i] = true;
+    }
+    let mut keep = keep.into_iter();
+    items.retain(|_| keep.next().unwrap_or(false));
+}
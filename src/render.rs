@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::badges;
+use crate::color::{Painter, Role};
+use std::os::unix::fs::MetadataExt;
+
+use crate::columns::{self, CellFormat, Column};
+use crate::devices;
+use crate::dired;
+use crate::emit;
+use crate::environment::{Environment, System};
+use crate::entry::{self, EnrichedEntry, EntryKind};
+use crate::filesystems;
+use crate::format;
+use crate::grid::{self, Direction};
+use crate::icons::{self, IconMode};
+use crate::messages;
+use crate::options::{Options, PathStyle, ShowErrors};
+use crate::sockets;
+use crate::statfmt;
+use crate::symlinks::{self, ChainEnd};
+use crate::sys;
+use crate::users;
+use crate::visited::Revisit;
+
+/// The fields shown for each entry, in order: those given by `--fields`,
+/// or the usual columns of the long listing (less those left out with
+/// `-g` and `-o`) followed by the name. The machine-readable outputs
+/// end with the "escaped" field with `--escape-names`.
+pub fn fields(options: &Options) -> Vec<Column> {
+    if options.output.is_machine() {
+        let mut fields = options.fields.clone().unwrap_or_else(|| columns::MACHINE_DEFAULT.to_vec());
+        if options.escape_names && !fields.contains(&Column::Escaped) {
+            fields.push(Column::Escaped);
+        }
+        return fields;
+    }
+    if let Some(fields) = &options.fields {
+        return fields.clone();
+    }
+    let mut fields = Vec::new();
+    if options.blocks {
+        fields.push(Column::Blocks);
+    }
+    if options.long {
+        fields.push(Column::Permissions);
+        if options.octal_permissions {
+            fields.push(Column::OctalPermissions);
+        }
+        fields.extend([Column::Links, Column::Owner, Column::Group, Column::Size, Column::Time]);
+    }
+    if options.filesystems {
+        fields.push(Column::Filesystem);
+    }
+    if options.trash_info {
+        fields.extend([Column::TrashDate, Column::TrashPath]);
+    }
+    fields.push(Column::Name);
+    fields.retain(|f| !options.omitted_fields.contains(f));
+    fields
+}
+
+/// How wide the fields have to be, by column, so that they line up.
+#[derive(Clone, Default, Debug)]
+pub struct Widths {
+    fields: Vec<(Column, usize)>,
+
+    /// of the major and minor numbers of devices
+    devices: (usize, usize)
+}
+
+impl Widths {
+
+    fn get(&self, column: Column) -> usize {
+        self.fields.iter().find(|(c, _)| *c == column).map_or(0, |(_, width)| *width)
+    }
+
+    /// Widens these to fit what the other ones fit too.
+    pub fn merge(&mut self, other: &Widths) {
+        for (column, width) in &other.fields {
+            match self.fields.iter_mut().find(|(c, _)| c == column) {
+                Some((_, w)) => { *w = (*w).max(*width) }
+                None => { self.fields.push((*column, *width)) }
+            }
+        }
+        self.devices.0 = self.devices.0.max(other.devices.0);
+        self.devices.1 = self.devices.1.max(other.devices.1);
+    }
+}
+
+/// How wide the bars of `--bar` are at most.
+const BAR_WIDTH: usize = 8;
+
+/// The widths over all the listed directories (`--align global`), which
+/// the renderers start from.
+static GLOBAL_WIDTHS: OnceLock<Widths> = OnceLock::new();
+
+pub fn set_global_widths(widths: Widths) {
+    let _ = GLOBAL_WIDTHS.set(widths);
+}
+
+/// Whether the entry is a regular file with more than one name, whose
+/// link count is highlighted.
+fn has_multiple_links(e: &EnrichedEntry) -> bool {
+    e.entry.kind == EntryKind::Regular && e.metadata.as_ref().is_some_and(|m| m.nlink() > 1)
+}
+
+/// Text as it is printed, and as it reads without the colors, which is
+/// what it is measured by.
+#[derive(Default)]
+struct Styled {
+    painted: String,
+    plain: String
+}
+
+impl Styled {
+
+    fn push(&mut self, text: &str) {
+        self.painted.push_str(text);
+        self.plain.push_str(text);
+    }
+
+    /// Adds the text, painted as given.
+    fn push_painted(&mut self, painted: &str, text: &str) {
+        self.painted.push_str(painted);
+        self.plain.push_str(text);
+    }
+}
+
+/// What most entries of a directory have in common, for `--anomalies`.
+#[derive(Default)]
+struct Majority {
+    owner: Option<u32>,
+
+    /// the permissions of most subdirectories, and of most other entries
+    /// (but symlinks, whose permissions mean nothing)
+    dir_mode: Option<u32>,
+    file_mode: Option<u32>
+}
+
+/// The value that more than half of them have, if one does.
+fn majority(values: impl Iterator<Item = u32>) -> Option<u32> {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    let mut total = 0;
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+        total += 1;
+    }
+    counts.into_iter().find(|(_, count)| count * 2 > total).map(|(value, _)| value)
+}
+
+/// Whether the entry is "." or "..", which belong to other directories.
+fn is_dot_entry(e: &EnrichedEntry) -> bool {
+    matches!(e.entry.get_name(), "." | "..")
+}
+
+/// The path, absolute and without symlinks if it exists.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).or_else(|_| std::path::absolute(path)).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The absolute path of the entry; the symlinks on the way to it are
+/// resolved, but not the entry itself.
+fn absolute_path(e: &EnrichedEntry) -> PathBuf {
+    match (e.path.parent(), e.path.file_name()) {
+        (Some(dir), Some(name)) => {
+            canonical(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }).join(name)
+        }
+        _ => { canonical(&e.path) }
+    }
+}
+
+/// The way from `base` to `path` (both absolute and normalized), e.g.
+/// "../lib/a.rs" from "/src/bin" to "/src/lib/a.rs".
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let common = path.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = base.components().skip(common).map(|_| Component::ParentDir).collect();
+    relative.extend(path.components().skip(common));
+    if relative.as_os_str().is_empty() { PathBuf::from(".") } else { relative }
+}
+
+/// Formats the entries of a single listed directory.
+pub struct Renderer<'a> {
+    options: &'a Options,
+
+    /// what to prefix the names with, if they are not shown alone
+    base: Option<PathBuf>,
+
+    /// the directory the paths are shown relative to (`--relative-to`),
+    /// absolute and without symlinks
+    relative_to: Option<PathBuf>,
+
+    /// whether the directory is on procfs, where processes get named
+    on_proc: bool,
+
+    /// the fields shown for each entry
+    fields: Vec<Column>,
+
+    /// width of each field, so that they line up
+    widths: Vec<usize>,
+
+    /// widths of the major and minor numbers of devices
+    device_widths: (usize, usize),
+
+    painter: Painter,
+
+    cell_format: CellFormat,
+
+    /// how wide the lines of the grid may get
+    line_length: Option<usize>,
+
+    /// whether the names are preceded by icons
+    show_icons: bool,
+
+    /// the current user and group, which are left out of the owner
+    /// and group columns (`--smart-owner`)
+    current_ids: Option<(u32, u32)>,
+
+    /// the size of the largest entry, which gets the full bar of `--bar`
+    largest: u64,
+
+    /// the owner and permissions the entries are expected to have
+    majority: Majority
+}
+
+impl<'a> Renderer<'a> {
+
+    pub fn new(options: &'a Options, query: &Path) -> Renderer<'a> {
+        Renderer::with_environment(options, query, &System)
+    }
+
+    /// Like `new()`, but with the time, the terminal and the variables
+    /// taken from `env`.
+    pub fn with_environment(options: &'a Options, query: &Path, env: &dyn Environment) -> Renderer<'a> {
+        let base = match options.path_style {
+            PathStyle::Name => { None }
+            PathStyle::Full => { Some(query.to_path_buf()) }
+            PathStyle::Canonical => {
+                Some(fs::canonicalize(query).unwrap_or_else(|_| query.to_path_buf()))
+            }
+        };
+        let relative_to = options.relative_to.as_ref().map(|dir| canonical(Path::new(dir)));
+        let mut fields = fields(options);
+
+        // sizes on /proc and /sys are zeros or made up
+        if options.fields.is_none() && !options.is_machine_output() && filesystems::is_pseudo(query) {
+            fields.retain(|f| *f != Column::Size);
+        }
+        let global = GLOBAL_WIDTHS.get().cloned().unwrap_or_default();
+        let widths = fields.iter().map(|f| global.get(*f)).collect();
+        let painter = Painter::new(options.color, options.theme, options.output_is_terminal(env), options.dim_others, env);
+
+        // GNU ls shows sizes in bytes unless asked otherwise
+        let cell_format = CellFormat {
+            now: env.now(),
+            human_sizes: options.human_sizes || !options.gnu,
+            grouped_sizes: options.bytes,
+            size_unit: options.size_unit,
+            relative_time: options.relative_time,
+            time_style: options.time_style,
+            mark_unnamed_ids: !options.gnu && users::lookup_enabled(),
+            escape_names: options.escape_names
+        };
+        let on_proc = filesystems::is_proc(query);
+        let line_length = options.line_length(env);
+
+        // GNU ls has no icons
+        let show_icons = !options.gnu && match options.icons {
+            IconMode::Always => { true }
+            IconMode::Never => { false }
+            IconMode::Auto => { options.output_is_terminal(env) }
+        };
+        let current_ids = (options.smart_owner && !options.is_machine_output())
+            .then(|| (sys::effective_uid(), sys::effective_gid()));
+        Renderer {
+            options, base, relative_to, on_proc, fields, widths, device_widths: global.devices, painter, cell_format,
+            line_length, show_icons, current_ids, largest: 0,
+            majority: Majority::default()
+        }
+    }
+
+    /// Whether printing an entry uses its metadata, even outside the
+    /// long listing (e.g. to color executables).
+    pub fn needs_metadata(&self) -> bool {
+        self.painter.is_enabled() || self.options.stat_format.is_some() || self.options.debug
+            || self.options.ipc_age || self.options.anomalies || self.fields.iter().any(|f| *f != Column::Name)
+    }
+
+    /// How the fields are formatted, with the time the listing started.
+    pub fn cell_format(&self) -> &CellFormat {
+        &self.cell_format
+    }
+
+    /// Measures the entries that are going to be printed, so that
+    /// the columns of the long listing can be aligned.
+    pub fn prepare(&mut self, entries: &[&EnrichedEntry]) {
+        if let Some((uid, gid)) = self.current_ids {
+
+            // the columns go if nothing in them would be shown
+            let owned = entries.iter().all(|e| e.uid().is_none_or(|id| id == uid));
+            let in_group = entries.iter().all(|e| e.gid().is_none_or(|id| id == gid));
+            let mut i = 0;
+            while i < self.fields.len() {
+                if (self.fields[i] == Column::Owner && owned) || (self.fields[i] == Column::Group && in_group) {
+                    self.fields.remove(i);
+                    self.widths.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        if self.options.anomalies {
+            let entries = || entries.iter().filter(|e| !is_dot_entry(e));
+            let modes = |dirs: bool| entries()
+                .filter(move |e| e.entry.is_directory() == dirs && !matches!(e.entry.kind, EntryKind::Symlink { .. }))
+                .filter_map(|e| e.metadata.as_ref().map(|m| m.mode() & 0o7777));
+            self.majority = Majority {
+                owner: majority(entries().filter_map(|e| e.uid())),
+                dir_mode: majority(modes(true)),
+                file_mode: majority(modes(false))
+            };
+        }
+        if self.options.bar {
+            let sizes = entries.iter().filter(|e| !e.entry.is_directory()).map(|e| e.size());
+            self.largest = self.largest.max(sizes.max().unwrap_or(0));
+        }
+        for e in entries {
+            if let Some((major, minor)) = columns::device_numbers(e) {
+                self.device_widths.0 = self.device_widths.0.max(major.to_string().len());
+                self.device_widths.1 = self.device_widths.1.max(minor.to_string().len());
+            }
+        }
+        for e in entries {
+            for (i, field) in self.fields.iter().enumerate() {
+                let width = match field {
+
+                    // the last field is not padded, so needs no measuring
+                    _ if i + 1 == self.fields.len() => { 0 }
+                    Column::Name => { format::display_width(&self.decorated_name(e).plain) }
+                    _ => { format::display_width(&self.cell(*field, e)) }
+                };
+                self.widths[i] = self.widths[i].max(width);
+            }
+        }
+    }
+
+    /// The widths measured so far.
+    pub fn measured(&self) -> Widths {
+        Widths { fields: self.fields.iter().copied().zip(self.widths.iter().copied()).collect(), devices: self.device_widths }
+    }
+
+    /// Whether the owner or group of the entry is another than the current
+    /// one, with `--smart-owner`.
+    fn is_foreign(&self, column: Column, e: &EnrichedEntry) -> bool {
+        match (column, self.current_ids) {
+            (Column::Owner, Some((uid, _))) => { e.uid().is_some_and(|id| id != uid) }
+            (Column::Group, Some((_, gid))) => { e.gid().is_some_and(|id| id != gid) }
+            _ => { false }
+        }
+    }
+
+    /// The cell of the column; device numbers are aligned among themselves.
+    fn cell(&self, column: Column, e: &EnrichedEntry) -> String {
+        let is_own = matches!(column, Column::Owner | Column::Group) && self.current_ids.is_some()
+            && e.metadata.is_some() && !self.is_foreign(column, e);
+        if is_own {
+            return String::new();
+        }
+        match columns::device_numbers(e) {
+            Some((major, minor)) if column == Column::Size && e.metadata.is_some() => {
+                let (major_width, minor_width) = self.device_widths;
+                format!("{major:>major_width$}, {minor:>minor_width$}")
+            }
+            _ => { column.cell(e, &self.cell_format) }
+        }
+    }
+
+    /// Prints the "total" line that heads the long listing in GNU mode
+    /// (and any listing with `-s`): the space allocated for the entries,
+    /// in kilobytes.
+    pub fn print_total(&self, out: &mut dyn Write, entries: &[&EnrichedEntry]) -> io::Result<()> {
+        let blocks: u64 = entries.iter()
+            .filter_map(|e| e.metadata.as_ref())
+            .map(|m| m.blocks())
+            .sum();
+        if self.options.dired {
+            write!(out, "  ")?;
+        }
+        if self.cell_format.human_sizes {
+            writeln!(out, "{}", messages::fill("total {}", &[&format::human_size(blocks * 512)]))
+        } else {
+            writeln!(out, "{}", messages::fill("total {}", &[&blocks.div_ceil(2)]))
+        }
+    }
+
+    /// Renders the given fields, each followed by a space.
+    fn cells(&self, e: &EnrichedEntry, range: std::ops::Range<usize>) -> String {
+        let mut text = String::new();
+        for (column, width) in self.fields[range.clone()].iter().zip(&self.widths[range]) {
+            let cell = self.cell(*column, e);
+            let padding = " ".repeat(width.saturating_sub(format::display_width(&cell)));
+            let cell = if column.is_right_aligned() { format!("{padding}{cell}") } else { format!("{cell}{padding}") };
+            if *column == Column::Links && has_multiple_links(e) {
+                text.push_str(&self.painter.paint_as(Role::MultipleLinks, &cell));
+            } else if self.is_foreign(*column, e) {
+                text.push_str(&self.painter.paint_as(Role::Foreign, &cell));
+            } else {
+                text.push_str(&cell);
+            }
+            text.push(' ');
+            if *column == Column::Size && self.options.bar {
+                text.push_str(&format!("{:<BAR_WIDTH$} ", self.size_bar(e)));
+            }
+        }
+        text
+    }
+
+    /// What is unusual about the entry in its directory, with `--anomalies`:
+    /// an owner or permissions other than most of the entries have.
+    fn anomaly(&self, e: &EnrichedEntry) -> Option<&'static str> {
+        let m = e.metadata.as_ref().filter(|_| self.options.anomalies && !is_dot_entry(e))?;
+        let owner = self.majority.owner.is_some_and(|uid| uid != m.uid());
+        let expected_mode = match e.entry.kind {
+            EntryKind::Symlink { .. } => { None }
+            _ if e.entry.is_directory() => { self.majority.dir_mode }
+            _ => { self.majority.file_mode }
+        };
+        let mode = expected_mode.is_some_and(|mode| mode != m.mode() & 0o7777);
+        match (owner, mode) {
+            (true, true) => { Some(messages::tr(" [unusual owner and permissions]")) }
+            (true, false) => { Some(messages::tr(" [unusual owner]")) }
+            (false, true) => { Some(messages::tr(" [unusual permissions]")) }
+            (false, false) => { None }
+        }
+    }
+
+    /// The bar of `--bar` after the size: as long as the size is, compared
+    /// with that of the largest entry. Directories and devices have none.
+    fn size_bar(&self, e: &EnrichedEntry) -> String {
+        let has_size = e.metadata.is_some() && !e.entry.is_directory() && columns::device_numbers(e).is_none();
+        if !has_size || self.largest == 0 {
+            return String::new();
+        }
+        format::bar(e.size() as f64 / self.largest as f64, BAR_WIDTH)
+    }
+
+    /// The name of the entry as it should be displayed.
+    fn display_name(&self, e: &EnrichedEntry) -> String {
+        if let Some(dir) = &self.relative_to {
+            return relative_path(&absolute_path(e), dir).to_string_lossy().into_owned();
+        }
+        match &self.base {
+            Some(base) => { base.join(e.entry.get_name()).to_string_lossy().into_owned() }
+            None => { e.entry.get_name().to_string() }
+        }
+    }
+
+    /// The displayed name, shortened to fit `--max-name-width`.
+    fn shown_name(&self, e: &EnrichedEntry) -> String {
+        let name = self.display_name(e);
+        match self.options.max_name_width {
+            Some(width) => { format::truncate_name(&name, width) }
+            None => { name }
+        }
+    }
+
+    /// The command of the process, for the /proc/<pid> directories.
+    fn process_name(&self, e: &EnrichedEntry) -> Option<String> {
+        if !self.on_proc || self.options.gnu || !e.entry.is_directory() {
+            return None;
+        }
+        if !e.entry.get_name().bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let command = fs::read_to_string(e.path.join("comm")).ok()?;
+        Some(command.trim_end().to_string())
+    }
+
+    /// Renders the whole chain of symlinks, e.g. "b -> c (broken)".
+    fn chain_text(&self, e: &EnrichedEntry) -> String {
+        let chain = symlinks::follow_chain(&e.path);
+        let mut text = chain.targets.join(" -> ");
+        match chain.end {
+            ChainEnd::Resolved => { }
+            ChainEnd::Broken => { text.push_str(messages::tr(" (broken)")) }
+            ChainEnd::Loop => { text.push_str(messages::tr(" (loop)")) }
+            ChainEnd::Error => { text.push_str(messages::tr(" (unresolvable)")) }
+        }
+        text
+    }
+
+    /// The colored name with its icon, the notes about the entry and its
+    /// symlink target. Some of these read /proc or follow links, so they
+    /// are only looked up once for both the printing and the measuring.
+    fn decorated_name(&self, e: &EnrichedEntry) -> Styled {
+        let mut text = Styled::default();
+        if self.show_icons {
+            text.push(&format!("{} ", icons::icon(e)));
+        }
+        let name = self.shown_name(e);
+        text.push_painted(&self.painter.paint(e, &name), &name);
+        if self.options.dir_slash && e.entry.is_directory() && !e.entry.get_name().ends_with('/') {
+            text.push("/");
+        }
+        if e.stat_timed_out {
+            text.push(messages::tr(" (stat timed out)"));
+        }
+        if e.vanished {
+            text.push(messages::tr(" (deleted during listing)"));
+        }
+        if let Some(kind) = e.error.filter(|_| self.options.show_errors == ShowErrors::Inline) {
+            let reason = format!(" ({})", entry::error_reason(kind));
+            text.push_painted(&self.painter.dim(&reason), &reason);
+        }
+        if let Some(command) = self.process_name(e) {
+            text.push(&format!(" ({command})"));
+        }
+        if self.options.socket_info && matches!(e.entry.kind, EntryKind::Socket) {
+            text.push(&format!(" ({})", sockets::describe(&e.path)));
+        }
+
+        // leftovers of crashed daemons tend to be old
+        let is_ipc = matches!(e.entry.kind, EntryKind::Pipe | EntryKind::Socket);
+        if self.options.ipc_age && is_ipc && e.metadata.is_some() {
+            text.push(&messages::fill(" (modified {})", &[&format::relative_time(e.mtime(), self.cell_format.now)]));
+        }
+        if !self.options.gnu {
+            if let Some(device) = devices::describe(e) {
+                text.push(&format!(" ({device})"));
+            }
+        }
+        match e.revisit {
+            Some(Revisit::Loop) if !self.options.gnu => { text.push(messages::tr(" (loop)")) }
+            Some(Revisit::AlreadyListed) if !self.options.gnu => { text.push(messages::tr(" (already listed)")) }
+            _ => { }
+        }
+        if let Some(anomaly) = self.anomaly(e) {
+            text.push_painted(&self.painter.paint_as(Role::Foreign, anomaly), anomaly);
+        }
+        if e.entry.is_whiteout() && !self.options.gnu {
+            text.push(messages::tr(" (whiteout)"));
+        }
+        if self.options.badges && !self.options.gnu && e.entry.is_directory() {
+            for badge in badges::badges(&e.path) {
+                text.push(&format!(" [{badge}]"));
+            }
+        }
+
+        // GNU ls shows symlink targets only in the long listing
+        let show_target = !self.fields.contains(&Column::Target) && (self.options.long || !self.options.gnu);
+        match &e.entry.kind {
+            EntryKind::Symlink { .. } if show_target && self.options.follow_chain => {
+                text.push(&format!(" -> {}", self.chain_text(e)));
+            }
+            EntryKind::Symlink { target } if show_target => {
+                text.push(&format!(" -> {target}"));
+            }
+            _ => { }
+        }
+        if self.options.link_target_metadata && self.options.long && show_target {
+            if let Some(summary) = self.target_summary(e) {
+                let summary = format!(" ({summary})");
+                text.push_painted(&self.painter.dim(&summary), &summary);
+            }
+        }
+        text
+    }
+
+    /// The permissions, size and modification time of what the symlink
+    /// points to (`--link-target-metadata`); None if it cannot be reached.
+    fn target_summary(&self, e: &EnrichedEntry) -> Option<String> {
+        if !matches!(e.entry.kind, EntryKind::Symlink { .. }) {
+            return None;
+        }
+        let m = fs::metadata(&e.path).ok()?;
+        let size = if self.cell_format.human_sizes { format::human_size(m.len()) } else { m.len().to_string() };
+        let time = columns::time_text(m.mtime(), m.mtime_nsec(), &self.cell_format);
+        Some(format!("{} {size} {time}", format::permission_string(m.mode())))
+    }
+
+    /// Shows on stderr what the entry was taken for, and why (`--debug`).
+    fn print_debug(&self, e: &EnrichedEntry) {
+        let raw = match &e.metadata {
+            Some(m) => { format!("mode {:o}, rdev {:#x}", m.mode(), m.rdev()) }
+            None => { "no metadata".to_string() }
+        };
+        eprintln!("debug: {}: {raw}, type {}, {}", e.path.display(), e.entry.type_name(), icons::explain(e));
+    }
+
+    pub fn print_entry(&self, out: &mut dyn Write, e: &EnrichedEntry) -> io::Result<()> {
+        if self.options.debug {
+            self.print_debug(e);
+        }
+        if let Some(pieces) = &self.options.stat_format {
+            return statfmt::print_entry(out, pieces, e);
+        }
+        if self.options.output.is_machine() {
+            return emit::print_record(out, self.options.output, &self.fields, e, &self.cell_format);
+        }
+
+        // the fields before and after the name
+        let name_at = self.fields.iter().position(|f| *f == Column::Name);
+        let before = self.cells(e, 0..name_at.unwrap_or(self.fields.len()));
+        let after = match name_at {
+            Some(i) => { self.cells(e, i + 1..self.fields.len()) }
+            None => { String::new() }
+        };
+
+        // dired wants the lines indented, and to know where the names are
+        if self.options.dired && self.options.long {
+            write!(out, "  {before}")?;
+            dired::write_name(out, &self.display_name(e))?;
+            if let EntryKind::Symlink { target } = &e.entry.kind {
+                write!(out, " -> {target}")?;
+            }
+            return writeln!(out, "{}", format!(" {after}").trim_end());
+        }
+
+        let Some(name_at) = name_at else {
+            return writeln!(out, "{}", before.trim_end());
+        };
+        let name = self.decorated_name(e);
+        if after.is_empty() {
+            writeln!(out, "{before}{}", name.painted)
+        } else {
+            let padding = " ".repeat(self.widths[name_at].saturating_sub(format::display_width(&name.plain)));
+            writeln!(out, "{before}{}{padding} {}", name.painted, after.trim_end())
+        }
+    }
+
+    /// Prints the names in columns, in lines of at most `--width`.
+    pub fn print_grid(&self, out: &mut dyn Write, entries: &[&EnrichedEntry], direction: Direction) -> io::Result<()> {
+        let cells: Vec<(String, usize)> = entries.iter()
+            .inspect(|e| if self.options.debug { self.print_debug(e) })
+            .map(|e| {
+                let Styled { painted: name, plain } = self.decorated_name(e);
+                let width = format::display_width(&plain);
+
+                // with -s, the blocks go before the name, as in a line
+                match self.fields.iter().position(|f| *f == Column::Blocks) {
+                    Some(i) => {
+                        let blocks_width = self.widths[i];
+                        (format!("{:>blocks_width$} {name}", self.cell(Column::Blocks, e)), blocks_width + 1 + width)
+                    }
+                    None => { (name, width) }
+                }
+            })
+            .collect();
+
+        // GNU ls pads with tabs where it can
+        grid::print_grid(out, &cells, self.line_length, direction, self.options.gnu)
+    }
+}
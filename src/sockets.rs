@@ -0,0 +1,75 @@
+//! What is behind UNIX socket files (`--socket-info`), from the table
+//! of the kernel in /proc/net/unix: whether anything is listening on
+//! them, or they are stale leftovers of daemons that are gone.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::messages;
+
+/// The flag of listening sockets (__SO_ACCEPTCON).
+const ACCEPTING: u32 = 0x10000;
+
+/// A socket bound to a path, as one line of /proc/net/unix shows it.
+struct UnixSocket {
+    flags: u32,
+
+    /// SOCK_STREAM, SOCK_DGRAM, ...
+    kind: u32,
+
+    /// SS_UNCONNECTED, SS_CONNECTING, ...
+    state: u32
+}
+
+/// The sockets of the table by path; one path can have several, e.g.
+/// the listening socket and the connections it accepted.
+fn sockets() -> &'static HashMap<PathBuf, Vec<UnixSocket>> {
+    static SOCKETS: OnceLock<HashMap<PathBuf, Vec<UnixSocket>>> = OnceLock::new();
+    SOCKETS.get_or_init(|| {
+        let mut sockets: HashMap<PathBuf, Vec<UnixSocket>> = HashMap::new();
+        let content = fs::read_to_string("/proc/net/unix").unwrap_or_default();
+
+        // Num RefCount Protocol Flags Type St Inode Path
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 8 {
+                continue;
+            }
+            let hex = |s: &str| u32::from_str_radix(s, 16).unwrap_or(0);
+            let socket = UnixSocket { flags: hex(fields[3]), kind: hex(fields[4]), state: hex(fields[5]) };
+            // the path is the rest of the line, and may contain spaces
+            sockets.entry(PathBuf::from(fields[7..].join(" "))).or_default().push(socket);
+        }
+        sockets
+    })
+}
+
+fn kind_name(kind: u32) -> &'static str {
+    match kind {
+        1 => { messages::tr("stream") }
+        2 => { messages::tr("datagram") }
+        5 => { messages::tr("seqpacket") }
+        _ => { messages::tr("unknown type") }
+    }
+}
+
+/// Describes the socket at the path, e.g. "listening, stream, 2 connected".
+pub fn describe(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let Some(sockets) = sockets().get(&path) else {
+        return messages::tr("stale, nothing bound").to_string();
+    };
+    let connected = sockets.iter().filter(|s| s.state == 3).count();
+    let kind = kind_name(sockets[0].kind);
+    let mut text = if sockets.iter().any(|s| s.flags & ACCEPTING != 0) {
+        messages::fill("listening, {}", &[&kind])
+    } else {
+        messages::fill("bound, {}", &[&kind])
+    };
+    if connected > 0 {
+        text.push_str(&messages::fill(", {} connected", &[&connected]));
+    }
+    text
+}
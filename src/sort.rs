@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use crate::entry::EnrichedEntry;
+use crate::sys;
+use crate::users;
+
+/// A single key of the sort order; several keys can be combined,
+/// e.g. `--sort type,size,name`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    Name,
+
+    /// names regardless of case
+    NameIgnoringCase,
+
+    /// names with the numbers in them compared by value, like `ls -v`
+    Version,
+    Size,
+    Time,
+    Type,
+    Extension,
+    Owner,
+    Group,
+
+    /// the number of entries in directories
+    Entries
+}
+
+impl SortKey {
+
+    pub fn parse(s: &str) -> Option<SortKey> {
+        match s {
+            "name" => { Some(SortKey::Name) }
+            "iname" => { Some(SortKey::NameIgnoringCase) }
+            "version" => { Some(SortKey::Version) }
+            "size" => { Some(SortKey::Size) }
+            "time" => { Some(SortKey::Time) }
+            "type" => { Some(SortKey::Type) }
+            "extension" => { Some(SortKey::Extension) }
+            "owner" => { Some(SortKey::Owner) }
+            "group" => { Some(SortKey::Group) }
+            "entries" => { Some(SortKey::Entries) }
+            _ => { None }
+        }
+    }
+
+    /// Whether sorting by the key needs more than the name and the type.
+    pub fn needs_metadata(&self) -> bool {
+        matches!(self, SortKey::Size | SortKey::Time | SortKey::Owner | SortKey::Group)
+    }
+
+    /// Whether the key is compared by what is precomputed for each entry
+    /// (see `Precomputed`) rather than by a `Comparator`.
+    fn is_precomputed(&self) -> bool {
+        matches!(self, SortKey::NameIgnoringCase | SortKey::Version | SortKey::Entries)
+    }
+}
+
+/// Parses a comma-separated list of sort keys; "none" gives an empty
+/// list, which means the entries stay in directory order.
+pub fn parse_sort_keys(spec: &str) -> Result<Vec<SortKey>, String> {
+    let mut keys = Vec::new();
+    if spec == "none" {
+        return Ok(keys);
+    }
+    for word in spec.split(',') {
+        match SortKey::parse(word.trim()) {
+            Some(key) => { keys.push(key) }
+            None => { return Err(format!("Unknown sort key '{word}'")) }
+        }
+    }
+    Ok(keys)
+}
+
+type Comparator = fn(&EnrichedEntry, &EnrichedEntry) -> Ordering;
+
+fn compare_name(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    a.entry.get_name().cmp(b.entry.get_name())
+}
+
+// largest first, like ls -S
+fn compare_size(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    b.size().cmp(&a.size())
+}
+
+// newest first, like ls -t
+fn compare_time(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    b.mtime().cmp(&a.mtime())
+}
+
+fn compare_type(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    a.entry.type_rank().cmp(&b.entry.type_rank())
+}
+
+// entries without extension go first
+fn compare_extension(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    a.entry.get_extension().cmp(&b.entry.get_extension())
+}
+
+/// Compares names of owners or groups; entries whose metadata could not
+/// be read go last.
+fn compare_ids(a: Option<u32>, b: Option<u32>, name: fn(u32) -> Option<&'static str>) -> Ordering {
+
+    // only ids without a name need a string made for them
+    let resolve = |id: u32| name(id).map_or_else(|| Cow::Owned(id.to_string()), Cow::Borrowed);
+    match (a, b) {
+        (Some(a), Some(b)) => { resolve(a).cmp(&resolve(b)) }
+        (Some(_), None) => { Ordering::Less }
+        (None, Some(_)) => { Ordering::Greater }
+        (None, None) => { Ordering::Equal }
+    }
+}
+
+fn compare_owner(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    compare_ids(a.uid(), b.uid(), users::user_name)
+}
+
+fn compare_group(a: &EnrichedEntry, b: &EnrichedEntry) -> Ordering {
+    compare_ids(a.gid(), b.gid(), users::group_name)
+}
+
+/// How many entries the directory has; None for other entries, and for
+/// directories that cannot be read.
+fn entry_count(e: &EnrichedEntry) -> Option<usize> {
+    if !e.entry.is_directory() {
+        return None;
+    }
+    e.open_dir().and_then(|dir| dir.entries()).ok().map(|entries| entries.count())
+}
+
+// fullest first, then the other entries
+fn compare_counts(a: Option<usize>, b: Option<usize>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => { b.cmp(&a) }
+        (Some(_), None) => { Ordering::Less }
+        (None, Some(_)) => { Ordering::Greater }
+        (None, None) => { Ordering::Equal }
+    }
+}
+
+/// A part of a name as version sorting compares it: a run of digits, by
+/// its value, or a run of anything else. Numbers go before text, as
+/// digits do before letters.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum VersionPart {
+
+    /// the number of digits without the leading zeros, then those digits
+    Number(usize, String),
+    Text(String)
+}
+
+/// The name split into the parts version sorting compares.
+fn version_key(name: &str) -> Vec<VersionPart> {
+    let mut parts = Vec::new();
+    let mut rest = name;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        if digits {
+            let value = part.trim_start_matches('0');
+            parts.push(VersionPart::Number(value.len(), value.to_string()));
+        } else {
+            parts.push(VersionPart::Text(part.to_string()));
+        }
+        rest = tail;
+    }
+    parts
+}
+
+/// What the keys that are costly to compare are compared by, made once
+/// per entry before sorting, so that each comparison only compares these;
+/// each is only made if a key needs it.
+struct Precomputed {
+
+    /// the collation key of the name (with `collated`)
+    name: Vec<u8>,
+
+    /// the name in lowercase, as a collation key with `collated`
+    folded: Vec<u8>,
+
+    version: Vec<VersionPart>,
+
+    /// the number of entries of directories
+    count: Option<usize>
+}
+
+impl Precomputed {
+
+    fn of(e: &EnrichedEntry, keys: &[SortKey], collated: bool) -> Precomputed {
+        let name = e.entry.get_name();
+        let collate = |name: &str| if collated { sys::collation_key(name) } else { name.as_bytes().to_vec() };
+        Precomputed {
+            name: if collated { collate(name) } else { Vec::new() },
+            folded: if keys.contains(&SortKey::NameIgnoringCase) { collate(&name.to_lowercase()) } else { Vec::new() },
+            version: if keys.contains(&SortKey::Version) { version_key(name) } else { Vec::new() },
+            count: if keys.contains(&SortKey::Entries) { entry_count(e) } else { None }
+        }
+    }
+}
+
+/// The comparator of the key; for the keys that need more than the
+/// entries (names collated, folded or split into versions, the entries
+/// of directories), see `sort_entries()`.
+fn comparator(key: SortKey) -> Comparator {
+    match key {
+        SortKey::Name => { compare_name }
+        SortKey::Size => { compare_size }
+        SortKey::Time => { compare_time }
+        SortKey::Type => { compare_type }
+        SortKey::Extension => { compare_extension }
+        SortKey::Owner => { compare_owner }
+        SortKey::Group => { compare_group }
+        SortKey::NameIgnoringCase | SortKey::Version | SortKey::Entries => { |_, _| Ordering::Equal }
+    }
+}
+
+/// Puts the entries into the given order: the entry at `order[i]` moves
+/// to position `i`. Each cycle of the permutation is walked once.
+fn apply_order(entries: &mut [EnrichedEntry], order: &[usize]) {
+    let mut placed = vec![false; order.len()];
+    for start in 0..order.len() {
+        let mut i = start;
+        while !placed[i] {
+            placed[i] = true;
+            let from = order[i];
+            if from == start {
+                break;
+            }
+            entries.swap(i, from);
+            i = from;
+        }
+    }
+}
+
+/// Sorts the entries by the given keys, evaluated in order.
+///
+/// Entries that are equal under all keys are ordered by name; should even
+/// the names be equal, the order in which they were read is kept (the sort
+/// is stable). With `reverse`, the whole resulting order is reversed.
+/// Without any keys, the entries are left untouched.
+///
+/// Names are compared byte by byte, or with `collated`, in the collation
+/// order of the locale (see `sys::use_locale_collation()`). Collation is
+/// costly, so the collation key of each name is made once, up front, and
+/// the comparisons only compare the keys. So are the names folded to
+/// lowercase, split into versions, and the entries of directories counted
+/// for the other keys that need it (see `Precomputed`).
+pub fn sort_entries(entries: &mut [EnrichedEntry], keys: &[SortKey], reverse: bool, collated: bool) {
+    if keys.is_empty() {
+        return;
+    }
+    if !collated && !keys.iter().any(SortKey::is_precomputed) {
+        let mut comparators: Vec<Comparator> = keys.iter().map(|k| comparator(*k)).collect();
+        comparators.push(compare_name);
+        entries.sort_by(|a, b| {
+            let ordering = comparators.iter()
+                .map(|cmp| cmp(a, b))
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal);
+            if reverse { ordering.reverse() } else { ordering }
+        });
+        return;
+    }
+
+    // sorts the positions of the entries, looking what was precomputed up
+    let precomputed: Vec<Precomputed> = entries.iter().map(|e| Precomputed::of(e, keys, collated)).collect();
+    let compare = |key: SortKey, a: usize, b: usize| {
+        let (a_keys, b_keys) = (&precomputed[a], &precomputed[b]);
+        match key {
+            SortKey::Name if collated => { a_keys.name.cmp(&b_keys.name) }
+            SortKey::NameIgnoringCase => { a_keys.folded.cmp(&b_keys.folded) }
+            SortKey::Version => { a_keys.version.cmp(&b_keys.version) }
+            SortKey::Entries => { compare_counts(a_keys.count, b_keys.count) }
+            _ => { comparator(key)(&entries[a], &entries[b]) }
+        }
+    };
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ordering = keys.iter().chain([&SortKey::Name])
+            .map(|key| compare(*key, a, b))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal);
+        if reverse { ordering.reverse() } else { ordering }
+    });
+    apply_order(entries, &order);
+}
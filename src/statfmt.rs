@@ -0,0 +1,109 @@
+//! Output in the style of `stat --format` (`--stat-format`), one line
+//! per entry with the percent escapes replaced by its metadata.
+
+use std::io::{self, Write};
+
+use crate::entry::{EnrichedEntry, EntryKind};
+use crate::format;
+use crate::users;
+
+/// The escapes that are understood, e.g. 's' for "%s".
+const ESCAPES: &str = "nNsYyaAFUGughibfo";
+
+/// A part of the format string.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Piece {
+    Text(String),
+
+    /// the character after the percent sign
+    Field(char)
+}
+
+/// Parses the format; "%%" stands for a percent sign.
+pub fn parse(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => { text.push('%') }
+            Some(f) if ESCAPES.contains(f) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Field(f));
+            }
+            Some(f) => { return Err(format!("Unknown escape '%{f}' in '--stat-format'")) }
+            None => { return Err("Incomplete escape at the end of '--stat-format'".to_string()) }
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// The type of the entry, in the words of `stat`.
+fn file_type(e: &EnrichedEntry) -> &'static str {
+    match e.entry.kind {
+        EntryKind::Directory => { "directory" }
+        EntryKind::Symlink { .. } => { "symbolic link" }
+        EntryKind::Regular if e.size() == 0 => { "regular empty file" }
+        EntryKind::Regular => { "regular file" }
+        EntryKind::Pipe => { "fifo" }
+        EntryKind::Socket => { "socket" }
+        EntryKind::CharDevice { .. } => { "character special file" }
+        EntryKind::BlockDevice { .. } => { "block special file" }
+        EntryKind::Unknown => { "unknown" }
+    }
+}
+
+/// The value of a field, as `stat` shows it.
+fn field(e: &EnrichedEntry, f: char) -> String {
+    let path = e.path.to_string_lossy().into_owned();
+    match f {
+        'n' => { return path }
+        'N' => {
+            return match &e.entry.kind {
+                EntryKind::Symlink { target } => { format!("'{path}' -> '{target}'") }
+                _ => { format!("'{path}'") }
+            };
+        }
+        _ => { }
+    }
+    let Some(m) = &e.metadata else {
+        return "?".to_string();
+    };
+    match f {
+        's' => { m.size().to_string() }
+        'Y' => { m.mtime().to_string() }
+        'y' => { format::iso_time(m.mtime()) }
+        'a' => { format!("{:o}", m.mode() & 0o7777) }
+        'A' => { format::permission_string(m.mode()) }
+        'U' => { users::user_name(m.uid()).map_or_else(|| m.uid().to_string(), str::to_string) }
+        'G' => { users::group_name(m.gid()).map_or_else(|| m.gid().to_string(), str::to_string) }
+        'u' => { m.uid().to_string() }
+        'g' => { m.gid().to_string() }
+        'h' => { m.nlink().to_string() }
+        'i' => { m.ino().to_string() }
+        'b' => { m.blocks().to_string() }
+        'f' => { format!("{:x}", m.mode()) }
+        'F' => { file_type(e).to_string() }
+        'o' => { m.blksize().to_string() }
+        _ => { String::new() }
+    }
+}
+
+pub fn print_entry(out: &mut dyn Write, pieces: &[Piece], e: &EnrichedEntry) -> io::Result<()> {
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => { write!(out, "{text}")? }
+            Piece::Field(f) => { write!(out, "{}", field(e, *f))? }
+        }
+    }
+    writeln!(out)
+}
@@ -0,0 +1,102 @@
+//! Aggregate statistics about a listing, shown by `--stat` instead of the entries.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::columns::{self, CellFormat};
+use crate::entry::{EnrichedEntry, EntryKind};
+use crate::format;
+use crate::group;
+use crate::messages;
+
+pub struct Stats<'a> {
+    entries: usize,
+
+    /// (type rank, type title, count), ordered by rank
+    types: Vec<(u8, &'static str, usize)>,
+
+    /// sum of the sizes of regular files
+    total_size: u64,
+
+    largest: Option<&'a EnrichedEntry>,
+    newest: Option<&'a EnrichedEntry>,
+
+    /// number of regular files per extension ("" for none)
+    extensions: HashMap<String, usize>
+}
+
+impl<'a> Stats<'a> {
+
+    pub fn collect(entries: &[&'a EnrichedEntry]) -> Stats<'a> {
+        let mut stats = Stats {
+            entries: entries.len(),
+            types: Vec::new(),
+            total_size: 0,
+            largest: None,
+            newest: None,
+            extensions: HashMap::new()
+        };
+
+        for e in entries {
+            let rank = e.entry.type_rank();
+            match stats.types.iter_mut().find(|(r, _, _)| *r == rank) {
+                Some((_, _, count)) => { *count += 1 }
+                None => { stats.types.push((rank, group::type_title(&e.entry), 1)) }
+            }
+
+            if e.metadata.is_some() && stats.newest.is_none_or(|n| e.mtime() > n.mtime()) {
+                stats.newest = Some(e);
+            }
+
+            if e.entry.kind == EntryKind::Regular {
+                stats.total_size += e.size();
+                if stats.largest.is_none_or(|l| e.size() > l.size()) {
+                    stats.largest = Some(e);
+                }
+                let ext = e.entry.get_extension().unwrap_or_default().to_string();
+                *stats.extensions.entry(ext).or_insert(0) += 1;
+            }
+        }
+        stats.types.sort();
+        stats
+    }
+
+    /// Prints the statistics, with the newest time as the time column would show it.
+    pub fn print(&self, out: &mut dyn Write, fmt: &CellFormat) -> io::Result<()> {
+        let labels = ["Entries:", "Total size:", "Largest:", "Newest:"].map(messages::tr);
+        let [entries, total_size, largest, newest] = labels;
+        let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        writeln!(out, "{entries:<label_width$} {}", self.entries)?;
+        let width = self.types.iter().map(|(_, title, _)| title.chars().count() + 1).max().unwrap_or(0);
+        for (_, title, count) in &self.types {
+            writeln!(out, "  {:<width$} {}", format!("{title}:"), count)?;
+        }
+        writeln!(out, "{total_size:<label_width$} {} ({})", format::human_size(self.total_size),
+            messages::count("{} byte|{} bytes", self.total_size))?;
+        if let Some(e) = self.largest {
+            writeln!(out, "{largest:<label_width$} {} ({})", e.entry.get_name(), format::human_size(e.size()))?;
+        }
+        if let Some(e) = self.newest {
+            let nsecs = e.metadata.as_ref().map_or(0, |m| m.mtime_nsec());
+            writeln!(out, "{newest:<label_width$} {} ({})", e.entry.get_name(), columns::time_text(e.mtime(), nsecs, fmt))?;
+        }
+
+        if !self.extensions.is_empty() {
+
+            // most frequent first, then alphabetically
+            let mut extensions: Vec<(&String, &usize)> = self.extensions.iter().collect();
+            extensions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+            let labels: Vec<String> = extensions.iter()
+                .map(|(ext, _)| if ext.is_empty() { messages::tr("(none)").to_string() } else { format!(".{ext}") })
+                .collect();
+            let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+            writeln!(out, "{}", messages::tr("Extensions:"))?;
+            for (label, (_, count)) in labels.iter().zip(&extensions) {
+                writeln!(out, "  {label:<width$} {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The kernel gives up following symlinks after this many steps (ELOOP).
+const MAX_STEPS: usize = 40;
+
+/// How following a chain of symlinks ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChainEnd {
+
+    /// reached something that is not a symlink
+    Resolved,
+
+    /// the last target does not exist
+    Broken,
+
+    /// the last target was already visited
+    Loop,
+
+    /// a link could not be read, or there were too many steps
+    Error
+}
+
+/// The targets of a symlink, of the target's target, and so on.
+pub struct SymlinkChain {
+    pub targets: Vec<String>,
+    pub end: ChainEnd
+}
+
+/// Follows the symlink at `path` step by step until something else is reached.
+pub fn follow_chain(path: &Path) -> SymlinkChain {
+    let mut targets = Vec::new();
+    let mut visited = HashSet::<PathBuf>::new();
+    let mut current = path.to_path_buf();
+    visited.insert(current.clone());
+
+    loop {
+        if targets.len() >= MAX_STEPS {
+            return SymlinkChain { targets, end: ChainEnd::Error };
+        }
+        let target = match fs::read_link(&current) {
+            Ok(target) => { target }
+            Err(_) => { return SymlinkChain { targets, end: ChainEnd::Error } }
+        };
+        targets.push(target.to_string_lossy().into_owned());
+
+        // relative targets are relative to the directory containing the link
+        let next = match current.parent() {
+            Some(parent) => { parent.join(&target) }
+            None => { target }
+        };
+        if !visited.insert(next.clone()) {
+            return SymlinkChain { targets, end: ChainEnd::Loop };
+        }
+        match fs::symlink_metadata(&next) {
+            Err(_) => { return SymlinkChain { targets, end: ChainEnd::Broken } }
+            Ok(metadata) if !metadata.file_type().is_symlink() => {
+                return SymlinkChain { targets, end: ChainEnd::Resolved };
+            }
+            Ok(_) => { current = next }
+        }
+    }
+}
@@ -0,0 +1,677 @@
+//! The few libc calls that std does not expose. They are those of Linux
+//! (with glibc or musl): the layouts of the structs, the ioctl() requests
+//! and the flags below are Linux's, and my_ls runs on nothing else.
+
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::fs;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::raw::{c_char, c_int, c_long, c_ulong, c_void};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Broken-down time as filled in by localtime_r().
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *const c_char
+}
+
+/// What statfs() fills in; only the type (the first field) is used,
+/// the rest leaves enough room for the fields that follow it.
+#[repr(C)]
+struct StatFs {
+    f_type: c_long,
+    rest: [c_long; 31]
+}
+
+/// LC_COLLATE has this value in both glibc and musl.
+const LC_COLLATE: c_int = 3;
+
+extern "C" {
+    fn localtime_r(timep: *const i64, result: *mut Tm) -> *mut Tm;
+    fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+    fn strcoll(s1: *const c_char, s2: *const c_char) -> c_int;
+    fn strxfrm(dest: *mut c_char, src: *const c_char, n: usize) -> usize;
+    fn statfs(path: *const c_char, buf: *mut StatFs) -> c_int;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    fn geteuid() -> u32;
+    fn getegid() -> u32;
+    fn gethostname(name: *mut c_char, len: usize) -> c_int;
+    fn openat(dirfd: c_int, path: *const c_char, flags: c_int, ...) -> c_int;
+    fn fdopendir(fd: c_int) -> *mut c_void;
+    fn readdir(dir: *mut c_void) -> *mut Dirent;
+    fn closedir(dir: *mut c_void) -> c_int;
+    fn rewinddir(dir: *mut c_void);
+    fn readlinkat(dirfd: c_int, path: *const c_char, buf: *mut c_char, size: usize) -> isize;
+    fn statx(dirfd: c_int, path: *const c_char, flags: c_int, mask: u32, buf: *mut Statx) -> c_int;
+    fn __errno_location() -> *mut c_int;
+}
+
+// the inode numbers and offsets of readdir() are longs in glibc (unless
+// built for large files, which we are not), and 64-bit in musl
+#[cfg(target_env = "gnu")]
+type DirentIno = c_ulong;
+#[cfg(target_env = "gnu")]
+type DirentOff = c_long;
+#[cfg(not(target_env = "gnu"))]
+type DirentIno = u64;
+#[cfg(not(target_env = "gnu"))]
+type DirentOff = i64;
+
+/// What readdir() returns on Linux; only the type and the name are used.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct Dirent {
+    d_ino: DirentIno,
+    d_off: DirentOff,
+    d_reclen: u16,
+    d_type: u8,
+    d_name: [c_char; 256]
+}
+
+// the values of d_type
+const DT_FIFO: u8 = 1;
+const DT_CHR: u8 = 2;
+const DT_DIR: u8 = 4;
+const DT_BLK: u8 = 6;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+const DT_SOCK: u8 = 12;
+
+const ENOTDIR: c_int = 20;
+
+/// AT_FDCWD, for paths that are not relative to an open directory.
+const AT_FDCWD: c_int = -100;
+
+// the flags of statx()
+const AT_SYMLINK_NOFOLLOW: c_int = 0x100;
+const AT_EMPTY_PATH: c_int = 0x1000;
+const STATX_BASIC_STATS: u32 = 0x7ff;
+
+/// A timestamp as statx() fills it in.
+#[repr(C)]
+struct StatxTimestamp {
+    tv_sec: i64,
+    tv_nsec: u32,
+    reserved: i32
+}
+
+/// What statx() fills in; unlike struct stat, it is laid out the same on
+/// all architectures.
+#[repr(C)]
+struct Statx {
+    stx_mask: u32,
+    stx_blksize: u32,
+    stx_attributes: u64,
+    stx_nlink: u32,
+    stx_uid: u32,
+    stx_gid: u32,
+    stx_mode: u16,
+    spare0: u16,
+    stx_ino: u64,
+    stx_size: u64,
+    stx_blocks: u64,
+    stx_attributes_mask: u64,
+    stx_atime: StatxTimestamp,
+    stx_btime: StatxTimestamp,
+    stx_ctime: StatxTimestamp,
+    stx_mtime: StatxTimestamp,
+    stx_rdev_major: u32,
+    stx_rdev_minor: u32,
+    stx_dev_major: u32,
+    stx_dev_minor: u32,
+    spare: [u64; 14]
+}
+
+const O_CLOEXEC: c_int = 0o2000000;
+
+// these two differ on ARM
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+const O_DIRECTORY: c_int = 0o40000;
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+const O_NOFOLLOW: c_int = 0o100000;
+#[cfg(not(any(target_arch = "arm", target_arch = "aarch64")))]
+const O_DIRECTORY: c_int = 0o200000;
+#[cfg(not(any(target_arch = "arm", target_arch = "aarch64")))]
+const O_NOFOLLOW: c_int = 0o400000;
+
+/// The ioctl() request for the window size (Linux, most architectures).
+const TIOCGWINSZ: c_ulong = 0x5413;
+
+/// What TIOCGWINSZ fills in.
+#[repr(C)]
+#[derive(Default)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16
+}
+
+/// A point in time in the local timezone.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LocalTime {
+    pub year: i32,
+
+    /// 1 to 12
+    pub month: u32,
+
+    /// 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+
+    /// seconds east of UTC
+    pub utc_offset: i64
+}
+
+/// Converts seconds since the epoch to local time.
+pub fn local_time(secs: i64) -> LocalTime {
+    // SAFETY: Tm is plain data (the zone pointer is never dereferenced)
+    // and localtime_r() only writes into the struct we pass
+    let tm = unsafe {
+        let mut tm: Tm = std::mem::zeroed();
+        localtime_r(&secs, &mut tm);
+        tm
+    };
+    LocalTime {
+        year: tm.tm_year + 1900,
+        month: (tm.tm_mon + 1) as u32,
+        day: tm.tm_mday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        second: tm.tm_sec as u32,
+        utc_offset: tm.tm_gmtoff as i64
+    }
+}
+
+/// Makes `collate()` follow the collation order of the user's locale
+/// (LC_ALL, LC_COLLATE or LANG), instead of the "C" locale.
+pub fn use_locale_collation() {
+    // SAFETY: the empty string is a valid locale name, and we do not
+    // keep the returned pointer
+    unsafe {
+        setlocale(LC_COLLATE, c"".as_ptr());
+    }
+}
+
+/// Compares two strings as strcoll() does in the current locale.
+pub fn collate(a: &str, b: &str) -> Ordering {
+    match (CString::new(a), CString::new(b)) {
+        (Ok(a), Ok(b)) => {
+            // SAFETY: both are valid NUL-terminated strings
+            let result = unsafe { strcoll(a.as_ptr(), b.as_ptr()) };
+            result.cmp(&0)
+        }
+
+        // names cannot contain NUL, but just in case
+        _ => { a.cmp(b) }
+    }
+}
+
+/// The key strxfrm() makes of the string in the current locale: keys
+/// compare byte by byte as `collate()` compares the strings themselves.
+pub fn collation_key(s: &str) -> Vec<u8> {
+    let Ok(s) = CString::new(s) else {
+        return s.as_bytes().to_vec();
+    };
+    let mut key = Vec::<u8>::new();
+    loop {
+        // SAFETY: the source is NUL-terminated and strxfrm() writes at most
+        // key.capacity() bytes; with a zero size, it only returns the length
+        let len = unsafe { strxfrm(key.as_mut_ptr() as *mut c_char, s.as_ptr(), key.capacity()) };
+        if len < key.capacity() {
+            // SAFETY: strxfrm() has written len bytes and the terminating NUL
+            unsafe { key.set_len(len) };
+            return key;
+        }
+        key.reserve_exact(len + 1);
+    }
+}
+
+/// The magic number of the type of the filesystem the path is on
+/// (symlinks are followed), or `None` if statfs() fails.
+pub fn filesystem_magic(path: &Path) -> Option<u64> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: the path is NUL-terminated; StatFs is plain data, and large
+    // enough for the struct statfs of any Linux architecture
+    let (result, buf) = unsafe {
+        let mut buf: StatFs = std::mem::zeroed();
+        (statfs(path.as_ptr(), &mut buf), buf)
+    };
+
+    // the magic numbers are 32-bit, but f_type may be a negative long
+    (result == 0).then_some(buf.f_type as u64 & 0xffff_ffff)
+}
+
+/// The user the files are accessed as.
+pub fn effective_uid() -> u32 {
+    // SAFETY: geteuid() cannot fail and has no arguments
+    unsafe { geteuid() }
+}
+
+/// The group the files are accessed as (besides the supplementary ones).
+pub fn effective_gid() -> u32 {
+    // SAFETY: getegid() cannot fail and has no arguments
+    unsafe { getegid() }
+}
+
+/// The name of the machine, if it has one.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0 as c_char; 256];
+    // SAFETY: the buffer is as long as we say; its last byte stays 0, so
+    // the name is terminated even if gethostname() truncated it
+    let result = unsafe { gethostname(buf.as_mut_ptr(), buf.len() - 1) };
+    if result != 0 {
+        return None;
+    }
+    // SAFETY: the buffer is terminated (see above)
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    name.to_str().ok().filter(|name| !name.is_empty()).map(str::to_string)
+}
+
+/// The number of columns of the terminal on stdout, if it is one.
+pub fn terminal_width() -> Option<usize> {
+    let mut size = WinSize::default();
+    // SAFETY: TIOCGWINSZ only writes a struct winsize, which WinSize matches
+    let result = unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut WinSize) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+/// The type of a file, as the directory tells it or as its metadata does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    Pipe,
+    Socket,
+    CharDevice,
+    BlockDevice
+}
+
+impl FileType {
+
+    /// The type of d_type; `None` for DT_UNKNOWN, which filesystems give
+    /// when they do not keep the types in the directory.
+    fn of_dirent(d_type: u8) -> Option<FileType> {
+        match d_type {
+            DT_REG => { Some(FileType::Regular) }
+            DT_DIR => { Some(FileType::Directory) }
+            DT_LNK => { Some(FileType::Symlink) }
+            DT_FIFO => { Some(FileType::Pipe) }
+            DT_SOCK => { Some(FileType::Socket) }
+            DT_CHR => { Some(FileType::CharDevice) }
+            DT_BLK => { Some(FileType::BlockDevice) }
+            _ => { None }
+        }
+    }
+}
+
+impl From<fs::FileType> for FileType {
+    fn from(file_type: fs::FileType) -> FileType {
+        if file_type.is_dir() {
+            FileType::Directory
+        } else if file_type.is_symlink() {
+            FileType::Symlink
+        } else if file_type.is_fifo() {
+            FileType::Pipe
+        } else if file_type.is_socket() {
+            FileType::Socket
+        } else if file_type.is_char_device() {
+            FileType::CharDevice
+        } else if file_type.is_block_device() {
+            FileType::BlockDevice
+        } else {
+            FileType::Regular
+        }
+    }
+}
+
+/// The makedev() of glibc and musl: the device number as `st_rdev` and
+/// `MetadataExt::rdev()` give it.
+fn device_id(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32) | ((major & 0xfff) << 8) | ((minor & 0xffff_ff00) << 12) | (minor & 0xff)
+}
+
+/// The metadata of a file, as statx() gives it; read relative to an open
+/// directory (see `Dir::metadata_at()`), which `fs::Metadata` cannot be.
+/// Its methods are those of `fs::Metadata` and `MetadataExt` that the
+/// listing uses.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Metadata {
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u64,
+    uid: u32,
+    gid: u32,
+    rdev: u64,
+    size: u64,
+    blksize: u64,
+    blocks: u64,
+    mtime: i64,
+    mtime_nsec: i64
+}
+
+impl Metadata {
+
+    /// Calls statx() on the path relative to the directory, with the flags.
+    fn statx_at(dirfd: c_int, path: &OsStr, flags: c_int) -> io::Result<Metadata> {
+        let path = CString::new(path.as_bytes()).map_err(|_| io::ErrorKind::InvalidInput)?;
+        // SAFETY: the path is NUL-terminated, and Statx is plain data that
+        // matches the struct statx statx() fills in
+        let (result, buf) = unsafe {
+            let mut buf: Statx = std::mem::zeroed();
+            (statx(dirfd, path.as_ptr(), flags, STATX_BASIC_STATS, &mut buf), buf)
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Metadata {
+            dev: device_id(buf.stx_dev_major, buf.stx_dev_minor),
+            ino: buf.stx_ino,
+            mode: buf.stx_mode as u32,
+            nlink: buf.stx_nlink as u64,
+            uid: buf.stx_uid,
+            gid: buf.stx_gid,
+            rdev: device_id(buf.stx_rdev_major, buf.stx_rdev_minor),
+            size: buf.stx_size,
+            blksize: buf.stx_blksize as u64,
+            blocks: buf.stx_blocks,
+            mtime: buf.stx_mtime.tv_sec,
+            mtime_nsec: buf.stx_mtime.tv_nsec as i64
+        })
+    }
+
+    pub fn file_type(&self) -> FileType {
+        match self.mode & 0o170000 {
+            0o040000 => { FileType::Directory }
+            0o120000 => { FileType::Symlink }
+            0o010000 => { FileType::Pipe }
+            0o140000 => { FileType::Socket }
+            0o020000 => { FileType::CharDevice }
+            0o060000 => { FileType::BlockDevice }
+            _ => { FileType::Regular }
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == FileType::Directory
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == FileType::Symlink
+    }
+
+    pub fn dev(&self) -> u64 {
+        self.dev
+    }
+
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// The type and the permission bits, as `st_mode`.
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn blksize(&self) -> u64 {
+        self.blksize
+    }
+
+    /// The number of 512-byte blocks allocated.
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    pub fn mtime(&self) -> i64 {
+        self.mtime
+    }
+
+    pub fn mtime_nsec(&self) -> i64 {
+        self.mtime_nsec
+    }
+}
+
+impl From<fs::Metadata> for Metadata {
+    fn from(m: fs::Metadata) -> Metadata {
+        Metadata {
+            dev: m.dev(),
+            ino: m.ino(),
+            mode: m.mode(),
+            nlink: m.nlink(),
+            uid: m.uid(),
+            gid: m.gid(),
+            rdev: m.rdev(),
+            size: m.size(),
+            blksize: m.blksize(),
+            blocks: m.blocks(),
+            mtime: m.mtime(),
+            mtime_nsec: m.mtime_nsec()
+        }
+    }
+}
+
+/// An entry of a directory, as readdir() gives it.
+pub struct DirEntry {
+    pub name: OsString,
+
+    /// `None` if the directory does not tell
+    pub file_type: Option<FileType>
+}
+
+fn open_fd(dirfd: c_int, path: &OsStr, flags: c_int) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_bytes()).map_err(|_| io::ErrorKind::InvalidInput)?;
+    // SAFETY: the path is NUL-terminated; a non-negative result is
+    // a new file descriptor that nothing else owns
+    let fd = unsafe { openat(dirfd, path.as_ptr(), flags) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// An open directory. Its subdirectories and the metadata of its entries
+/// are looked up relative to it rather than by path, so that a directory
+/// renamed (or replaced with a symlink) while a tree is walked cannot
+/// lead the walk outside of the tree. Clones share the descriptor (see
+/// `try_clone()` for another one), so that the entries can keep theirs.
+#[derive(Clone)]
+pub struct Dir {
+    fd: Arc<OwnedFd>
+}
+
+impl Dir {
+
+    /// Opens the directory at the path; a symlink to one is followed.
+    pub fn open(path: &Path) -> io::Result<Dir> {
+        Dir::open_relative(AT_FDCWD, path.as_os_str(), O_DIRECTORY | O_CLOEXEC)
+    }
+
+    /// The directory open as the given descriptor, e.g. one inherited
+    /// from the parent process; the descriptor itself is left open.
+    pub fn inherited(fd: RawFd) -> io::Result<Dir> {
+        // SAFETY: the descriptor is only duplicated, which fails with
+        // EBADF if it is not open
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        let dir = Dir { fd: Arc::new(fd) };
+        if !dir.metadata()?.is_dir() {
+            return Err(io::Error::from_raw_os_error(ENOTDIR));
+        }
+        Ok(dir)
+    }
+
+    /// Opens the subdirectory of the given name; symlinks are not followed.
+    pub fn open_at(&self, name: &OsStr) -> io::Result<Dir> {
+        Dir::open_relative(self.fd.as_raw_fd(), name, O_DIRECTORY | O_NOFOLLOW | O_CLOEXEC)
+    }
+
+    /// Opens the subdirectory of the given name, or the directory the
+    /// symlink of that name points to (`--follow-symlinks`).
+    pub fn open_following_at(&self, name: &OsStr) -> io::Result<Dir> {
+        Dir::open_relative(self.fd.as_raw_fd(), name, O_DIRECTORY | O_CLOEXEC)
+    }
+
+    /// Opens the file at the path relative to the directory, for reading.
+    pub fn open_file_at(&self, path: &Path) -> io::Result<fs::File> {
+        Ok(fs::File::from(open_fd(self.fd.as_raw_fd(), path.as_os_str(), O_CLOEXEC)?))
+    }
+
+    fn open_relative(dirfd: c_int, path: &OsStr, flags: c_int) -> io::Result<Dir> {
+        Ok(Dir { fd: Arc::new(open_fd(dirfd, path, flags)?) })
+    }
+
+    /// Another descriptor of the same open directory, e.g. for another thread.
+    pub fn try_clone(&self) -> io::Result<Dir> {
+        Ok(Dir { fd: Arc::new(self.fd.try_clone()?) })
+    }
+
+    /// The entries, in directory order, without "." and "..".
+    pub fn entries(&self) -> io::Result<Entries> {
+
+        // fdopendir() takes over the descriptor it is given, so it gets a copy
+        let fd = self.fd.try_clone()?.into_raw_fd();
+        // SAFETY: fd is an open directory that the stream now owns
+        let stream = unsafe { fdopendir(fd) };
+        if stream.is_null() {
+            let err = io::Error::last_os_error();
+            // SAFETY: without a stream, the copy is still ours to close
+            drop(unsafe { OwnedFd::from_raw_fd(fd) });
+            return Err(err);
+        }
+
+        // the copy shares the position in the directory with the others,
+        // which an earlier reading may have left at the end
+        // SAFETY: the stream is open
+        unsafe { rewinddir(stream) };
+        Ok(Entries { stream, done: false })
+    }
+
+    /// The names of the entries, in directory order, without "." and "..".
+    pub fn names(&self) -> io::Result<Vec<OsString>> {
+        self.entries()?.map(|entry| entry.map(|entry| entry.name)).collect()
+    }
+
+    /// The metadata of the directory itself.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Metadata::statx_at(self.fd.as_raw_fd(), OsStr::new(""), AT_EMPTY_PATH)
+    }
+
+    /// The metadata of the entry of the given name; symlinks are not followed.
+    pub fn metadata_at(&self, name: &OsStr) -> io::Result<Metadata> {
+        Metadata::statx_at(self.fd.as_raw_fd(), name, AT_SYMLINK_NOFOLLOW)
+    }
+
+    /// The metadata of the entry of the given name, or of what the symlink
+    /// of that name points to.
+    pub fn metadata_following_at(&self, name: &OsStr) -> io::Result<Metadata> {
+        Metadata::statx_at(self.fd.as_raw_fd(), name, 0)
+    }
+
+    /// Where the symlink of the given name points to.
+    pub fn read_link_at(&self, name: &OsStr) -> io::Result<PathBuf> {
+        let name = CString::new(name.as_bytes()).map_err(|_| io::ErrorKind::InvalidInput)?;
+        let mut buf = Vec::<u8>::with_capacity(256);
+        loop {
+            // SAFETY: the name is NUL-terminated, and readlinkat() writes at
+            // most buf.capacity() bytes, without a terminating NUL
+            let len = unsafe {
+                readlinkat(self.fd.as_raw_fd(), name.as_ptr(), buf.as_mut_ptr() as *mut c_char, buf.capacity())
+            };
+            if len < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // a target that fills the buffer may have been cut short
+            if (len as usize) < buf.capacity() {
+                // SAFETY: readlinkat() has written len bytes
+                unsafe { buf.set_len(len as usize) };
+                return Ok(PathBuf::from(OsString::from_vec(buf)));
+            }
+            buf.reserve_exact(buf.capacity() * 2);
+        }
+    }
+}
+
+/// The entries of an open directory, read as they are asked for; see
+/// `Dir::entries()`. Like `fs::ReadDir`, it ends after an error.
+pub struct Entries {
+    stream: *mut c_void,
+    done: bool
+}
+
+impl Iterator for Entries {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<io::Result<DirEntry>> {
+        while !self.done {
+            // SAFETY: the stream is open, errno is this thread's, and the
+            // entry stays valid until the next readdir(); its name is
+            // NUL-terminated
+            let entry = unsafe {
+                let errno = __errno_location();
+
+                // only errno tells the end of the directory from an error
+                *errno = 0;
+                let dirent = readdir(self.stream);
+                if dirent.is_null() {
+                    self.done = true;
+                    if *errno != 0 {
+                        return Some(Err(io::Error::from_raw_os_error(*errno)));
+                    }
+                    return None;
+                }
+                let name = CStr::from_ptr((*dirent).d_name.as_ptr()).to_bytes().to_vec();
+                DirEntry { name: OsString::from_vec(name), file_type: FileType::of_dirent((*dirent).d_type) }
+            };
+            if entry.name != "." && entry.name != ".." {
+                return Some(Ok(entry));
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Entries {
+    fn drop(&mut self) {
+        // SAFETY: the stream is open, and is not used afterwards
+        unsafe { closedir(self.stream) };
+    }
+}
@@ -0,0 +1,54 @@
+//! How long the phases of the listing took, summed over all directories
+//! and reported on stderr at the end (`--timings`).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+
+    /// reading the names from the directories
+    ReadDir,
+
+    /// fetching the metadata of the entries
+    Metadata,
+    Sort,
+
+    /// filtering, grouping and printing
+    Render
+}
+
+const PHASES: [(Phase, &str); 4] = [
+    (Phase::ReadDir, "read_dir"),
+    (Phase::Metadata, "metadata"),
+    (Phase::Sort, "sorting"),
+    (Phase::Render, "rendering")
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Nanoseconds spent in each phase, in the order of `PHASES`.
+static NANOS: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Runs `f`, counting the time it takes to the phase if timings are on.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    let i = PHASES.iter().position(|(p, _)| *p == phase).unwrap();
+    NANOS[i].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    result
+}
+
+pub fn print_report() {
+    for (i, (_, name)) in PHASES.iter().enumerate() {
+        let ms = NANOS[i].load(Ordering::Relaxed) as f64 / 1e6;
+        eprintln!("{name:<10} {ms:>10.3} ms");
+    }
+}
@@ -0,0 +1,60 @@
+//! The footers of the recursive listing: how many entries each directory
+//! has and how big its files are, and the grand total at the end.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::entry::EnrichedEntry;
+use crate::format;
+use crate::messages;
+
+static DIRECTORIES: AtomicU64 = AtomicU64::new(0);
+static ENTRIES: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+fn entries_text(count: u64) -> String {
+    messages::count("{} entry|{} entries", count)
+}
+
+// "1.5K", but "12 bytes" rather than a bare number
+fn size_text(bytes: u64) -> String {
+    if bytes < 1024 { messages::count("{} byte|{} bytes", bytes) } else { format::human_size(bytes) }
+}
+
+/// Counts the entries shown for one directory.
+#[derive(Default)]
+pub struct Tally {
+    entries: u64,
+
+    /// sizes of the entries that are not directories (those have no
+    /// useful size of their own)
+    bytes: u64
+}
+
+impl Tally {
+
+    pub fn add(&mut self, e: &EnrichedEntry) {
+        self.entries += 1;
+        if !e.entry.is_directory() {
+            self.bytes += e.size();
+        }
+    }
+
+    /// Prints the footer of the directory, and adds it to the grand total.
+    pub fn print_footer(&self, out: &mut dyn Write) -> io::Result<()> {
+        DIRECTORIES.fetch_add(1, Ordering::Relaxed);
+        ENTRIES.fetch_add(self.entries, Ordering::Relaxed);
+        BYTES.fetch_add(self.bytes, Ordering::Relaxed);
+        writeln!(out, "{}", messages::fill("{}, {} in files", &[&entries_text(self.entries), &size_text(self.bytes)]))
+    }
+}
+
+pub fn print_grand_total(out: &mut dyn Write) -> io::Result<()> {
+    let directories = DIRECTORIES.load(Ordering::Relaxed);
+    let text = messages::fill("Total: {} in {}, {} in files", &[
+        &entries_text(ENTRIES.load(Ordering::Relaxed)),
+        &messages::count("{} directory|{} directories", directories),
+        &size_text(BYTES.load(Ordering::Relaxed))
+    ]);
+    writeln!(out, "{text}")
+}
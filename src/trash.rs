@@ -0,0 +1,101 @@
+//! Where the files in a desktop trash came from (`--trash-info`): for
+//! "<trash>/files/<name>", the freedesktop.org trash specification keeps
+//! the original path and the deletion date in "<trash>/info/<name>.trashinfo".
+
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+
+use crate::entry::EnrichedEntry;
+
+/// What the `.trashinfo` file says about a trashed file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TrashInfo {
+
+    /// absolute, or relative to the top of the volume for the trash
+    /// directories of other volumes
+    pub original_path: PathBuf,
+
+    /// local time, as "YYYY-MM-DDThh:mm:ss"
+    pub deleted: Option<String>
+}
+
+/// Decodes the %XX escapes of the path, which is written as in URLs.
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) if bytes[i] == b'%' => {
+                decoded.push(b);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// The directory relative paths are resolved against: the volume that
+/// holds "$topdir/.Trash/$uid" or "$topdir/.Trash-$uid".
+fn top_dir(trash: &Path) -> Option<&Path> {
+    let parent = trash.parent()?;
+    if parent.file_name().is_some_and(|n| n == ".Trash") {
+        parent.parent()
+    } else {
+        Some(parent)
+    }
+}
+
+/// Parses the `[Trash Info]` group of a `.trashinfo` file.
+fn parse(text: &str) -> Option<TrashInfo> {
+    let mut in_group = false;
+    let mut path = None;
+    let mut deleted = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == "[Trash Info]";
+            continue;
+        }
+        if !in_group {
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Path", value)) => { path = Some(PathBuf::from(OsString::from_vec(percent_decode(value)))) }
+            Some(("DeletionDate", value)) => { deleted = Some(value.to_string()) }
+            _ => { }
+        }
+    }
+    Some(TrashInfo { original_path: path?, deleted })
+}
+
+/// The trash info of the entry, if it is in the "files" directory of
+/// a trash and has its `.trashinfo`.
+pub fn info(e: &EnrichedEntry) -> Option<TrashInfo> {
+
+    // the directory may have been given as "." or the like
+    let parent = e.path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let files = fs::canonicalize(parent).ok()?;
+    if files.file_name()? != "files" {
+        return None;
+    }
+    let trash = files.parent()?;
+    let mut info_name = e.path.file_name()?.to_os_string();
+    info_name.push(".trashinfo");
+    let text = fs::read_to_string(trash.join("info").join(info_name)).ok()?;
+    let mut info = parse(&text)?;
+    if info.original_path.is_relative() {
+        if let Some(top) = top_dir(trash) {
+            info.original_path = top.join(&info.original_path);
+        }
+    }
+    Some(info)
+}
@@ -0,0 +1,65 @@
+//! The `--usage` report: how much space the files of each extension take.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::entry::EntryKind;
+use crate::format;
+use crate::messages;
+use crate::options::{Operand, Options};
+use crate::walk;
+
+const BAR_WIDTH: usize = 30;
+
+pub fn print_usage(out: &mut dyn Write, operand: &Operand, options: &Options) -> io::Result<()> {
+
+    // extension ("" for none) -> (total size, number of files)
+    let mut usage = HashMap::<String, (u64, usize)>::new();
+    let root = Path::new(&operand.path);
+    walk::walk_opened(root, operand.open(), options.recursive, &options.filters, &mut |e| {
+        if e.entry.kind == EntryKind::Regular {
+            let ext = e.entry.get_extension().unwrap_or_default().to_string();
+            let u = usage.entry(ext).or_insert((0, 0));
+            u.0 += e.size();
+            u.1 += 1;
+        }
+    });
+
+    // biggest first, then alphabetically
+    let mut rows: Vec<(String, u64, usize)> = usage.into_iter().map(|(ext, (size, count))| (ext, size, count)).collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let total: u64 = rows.iter().map(|r| r.1).sum();
+    let count: usize = rows.iter().map(|r| r.2).sum();
+    let largest = rows.first().map_or(0, |r| r.1);
+
+    let labels: Vec<String> = rows.iter()
+        .map(|r| if r.0.is_empty() { messages::tr("(none)").to_string() } else { format!(".{}", r.0) })
+        .collect();
+    let sizes: Vec<String> = rows.iter().map(|r| format::human_size(r.1)).collect();
+    let total_label = messages::tr("Total");
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0).max(total_label.chars().count());
+    let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0).max(format::human_size(total).len());
+
+    // the numbers of files right-aligned within "{} files"
+    let count_width = count.to_string().len();
+    let files = |n: usize| {
+        messages::count_form("{} file|{} files", n as u64).replace("{}", &format!("{n:>count_width$}"))
+    };
+    let files_width = rows.iter().map(|r| files(r.2).chars().count()).max().unwrap_or(0);
+
+    if options.paths.len() > 1 {
+        writeln!(out, "{}:", root.display())?;
+    }
+    for ((label, size), row) in labels.iter().zip(&sizes).zip(&rows) {
+        let fraction = if largest > 0 { row.1 as f64 / largest as f64 } else { 0.0 };
+        let line = format!("{label:<label_width$} {size:>size_width$} {:<files_width$} {}",
+            files(row.2), format::bar(fraction, BAR_WIDTH));
+
+        // no padding is left where there is no bar to line up
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    writeln!(out, "{total_label:<label_width$} {:>size_width$} {}", format::human_size(total), files(count))?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the names of the ids are looked up at all.
+static LOOKUP: AtomicBool = AtomicBool::new(true);
+
+/// Leaves all the ids without names (`--no-lookup`), which spares reading
+/// /etc/passwd and /etc/group; names given for ids have to be numbers.
+pub fn disable_lookup() {
+    LOOKUP.store(false, Ordering::Relaxed);
+}
+
+/// Whether names are looked up, so that an id without one has none.
+pub fn lookup_enabled() -> bool {
+    LOOKUP.load(Ordering::Relaxed)
+}
+
+/// Reads a passwd-style file (name:password:id:...) into a map from id to name.
+fn read_id_file(path: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    if let Ok(content) = fs::read_to_string(path) {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 { continue; }
+            if let Ok(id) = fields[2].parse::<u32>() {
+
+                // the first entry for an id wins, as with getpwuid()
+                names.entry(id).or_insert_with(|| fields[0].to_string());
+            }
+        }
+    }
+    names
+}
+
+fn users() -> &'static HashMap<u32, String> {
+    static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    USERS.get_or_init(|| if lookup_enabled() { read_id_file("/etc/passwd") } else { HashMap::new() })
+}
+
+fn groups() -> &'static HashMap<u32, String> {
+    static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    GROUPS.get_or_init(|| if lookup_enabled() { read_id_file("/etc/group") } else { HashMap::new() })
+}
+
+/// Finds the id for a name, or takes the name as a numeric id.
+fn find_id(names: &HashMap<u32, String>, name: &str) -> Option<u32> {
+    names.iter()
+        .filter(|(_, n)| *n == name)
+        .map(|(id, _)| *id)
+        .min()
+        .or_else(|| name.parse::<u32>().ok())
+}
+
+/// Returns the name of the user with the given id, if there is one.
+pub fn user_name(uid: u32) -> Option<&'static str> {
+    if !lookup_enabled() {
+        return None;
+    }
+    users().get(&uid).map(String::as_str)
+}
+
+/// Returns the name of the group with the given id, if there is one.
+pub fn group_name(gid: u32) -> Option<&'static str> {
+    if !lookup_enabled() {
+        return None;
+    }
+    groups().get(&gid).map(String::as_str)
+}
+
+/// Returns the id of the user with the given name (or numeric id).
+pub fn user_id(name: &str) -> Option<u32> {
+    find_id(users(), name)
+}
+
+/// Returns the id of the group with the given name (or numeric id).
+pub fn group_id(name: &str) -> Option<u32> {
+    find_id(groups(), name)
+}
@@ -0,0 +1,70 @@
+//! The directories that the recursive listing has been in, so that it does
+//! not go round in circles through symlinks (`a -> .`) or bind mounts.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use crate::sys::Dir;
+
+/// Why a directory is not listed again.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Revisit {
+
+    /// it contains itself, so there would be no end to it
+    Loop,
+
+    /// it was reached another way before
+    AlreadyListed
+}
+
+#[derive(Default)]
+struct Visited {
+
+    /// (device, inode) of all directories listed so far
+    seen: HashSet<(u64, u64)>,
+
+    /// the directories being listed, from the top one down
+    ancestors: Vec<Option<(u64, u64)>>
+}
+
+thread_local! {
+    static VISITED: RefCell<Visited> = RefCell::new(Visited::default());
+}
+
+/// The (device, inode) of the directory, following symlinks.
+fn id(dir: &Path) -> Option<(u64, u64)> {
+    fs::metadata(dir).ok().map(|m| (m.dev(), m.ino()))
+}
+
+/// Records that the directory is being listed; `leave()` it once its
+/// subdirectories are done.
+pub fn enter(dir: &Dir) {
+    let id = dir.metadata().ok().map(|m| (m.dev(), m.ino()));
+    VISITED.with(|v| {
+        let mut v = v.borrow_mut();
+        v.seen.extend(id);
+        v.ancestors.push(id);
+    });
+}
+
+pub fn leave() {
+    VISITED.with(|v| v.borrow_mut().ancestors.pop());
+}
+
+/// Whether the directory has been listed already.
+pub fn revisit(dir: &Path) -> Option<Revisit> {
+    let id = id(dir)?;
+    VISITED.with(|v| {
+        let v = v.borrow();
+        if v.ancestors.contains(&Some(id)) {
+            Some(Revisit::Loop)
+        } else if v.seen.contains(&id) {
+            Some(Revisit::AlreadyListed)
+        } else {
+            None
+        }
+    })
+}
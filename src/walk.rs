@@ -0,0 +1,66 @@
+//! Traversal of directory trees for the reports that aggregate over them.
+//! Each subdirectory is opened relative to its parent, and the metadata
+//! of the entries is read relative to their directory, so that renames
+//! during the walk cannot take it outside of the tree.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::entry::EnrichedEntry;
+use crate::filter::{self, Filter};
+use crate::messages;
+use crate::sys::Dir;
+
+static HAD_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Whether any directory could not be read by the walks so far.
+pub fn had_errors() -> bool {
+    HAD_ERRORS.load(Ordering::Relaxed)
+}
+
+fn report(message: &'static str, path: &Path, err: &io::Error) {
+    eprintln!("{}", messages::fill(message, &[&path.display(), err]));
+    HAD_ERRORS.store(true, Ordering::Relaxed);
+}
+
+/// Calls `visit` for every entry in `root` that passes the filters; with
+/// `recursive`, also for the entries of all its subdirectories (symlinks
+/// to directories are not followed). Directories that cannot be read
+/// are reported on stderr and skipped.
+pub fn walk(root: &Path, recursive: bool, filters: &[Filter], visit: &mut dyn FnMut(&EnrichedEntry)) {
+    walk_opened(root, Dir::open(root), recursive, filters, visit);
+}
+
+/// Like `walk()`, for a directory the caller has opened (or failed to),
+/// shown as `root`.
+pub fn walk_opened(root: &Path, opened: io::Result<Dir>, recursive: bool, filters: &[Filter],
+                   visit: &mut dyn FnMut(&EnrichedEntry)) {
+    match opened {
+        Ok(dir) => { walk_dir(&dir, root, recursive, filters, visit) }
+        Err(err) => { report("Could not open '{}': {}", root, &err) }
+    }
+}
+
+fn walk_dir(dir: &Dir, path: &Path, recursive: bool, filters: &[Filter], visit: &mut dyn FnMut(&EnrichedEntry)) {
+    let names = match dir.names() {
+        Ok(names) => { names }
+        Err(err) => {
+            report("Could not read '{}': {}", path, &err);
+            return;
+        }
+    };
+
+    for name in names {
+        let e = EnrichedEntry::in_dir(dir, path, &name);
+        if filter::matches_all(filters, &e) {
+            visit(&e);
+        }
+        if recursive && e.entry.is_directory() {
+            match dir.open_at(&name) {
+                Ok(subdir) => { walk_dir(&subdir, &e.path, recursive, filters, visit) }
+                Err(err) => { report("Could not open '{}': {}", &e.path, &err) }
+            }
+        }
+    }
+}
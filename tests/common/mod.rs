@@ -0,0 +1,104 @@
+//! The fixture directories the integration tests list.
+
+// each test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use std::ffi::{CString, OsStr};
+use std::fs;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use my_ls::entry::EnrichedEntry;
+use my_ls::group::{self, GroupBy};
+use my_ls::sort::{self, SortKey};
+
+extern "C" {
+    fn mkfifo(path: *const c_char, mode: c_int) -> c_int;
+}
+
+/// A directory under the system temp directory, removed when dropped.
+pub struct Fixture {
+    pub dir: PathBuf
+}
+
+impl Fixture {
+
+    pub fn new() -> Fixture {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("my_ls-test-{}-{n}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Fixture { dir }
+    }
+
+    /// One entry of each kind that can be made without privileges.
+    pub fn with_all_kinds() -> Fixture {
+        let fixture = Fixture::new();
+        fixture.file("regular", b"some text");
+        fixture.file("empty", b"");
+        fs::create_dir(fixture.dir.join("subdir")).unwrap();
+        symlink("regular", fixture.dir.join("link")).unwrap();
+        symlink("nowhere", fixture.dir.join("dangling")).unwrap();
+        fixture.fifo("pipe");
+        fixture.socket("socket");
+        fixture
+    }
+
+    pub fn file(&self, name: impl AsRef<OsStr>, content: &[u8]) {
+        fs::write(self.dir.join(name.as_ref()), content).unwrap();
+    }
+
+    /// Sets the permissions and the modification time (in seconds since
+    /// the epoch) of the entry, so that they do not depend on the umask
+    /// and the clock.
+    pub fn fix_metadata(&self, name: &str, mode: u32, mtime: i64) {
+        let path = self.dir.join(name);
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime as u64)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    pub fn fifo(&self, name: &str) {
+        let path = CString::new(self.dir.join(name).as_os_str().as_bytes()).unwrap();
+        // SAFETY: the path is NUL-terminated and outlives the call
+        assert_eq!(unsafe { mkfifo(path.as_ptr(), 0o644) }, 0);
+    }
+
+    pub fn socket(&self, name: &str) {
+
+        // the socket file stays after the listener is dropped
+        UnixListener::bind(self.dir.join(name)).unwrap();
+    }
+
+    /// The entries of the directory, in the order `read_dir` gives them.
+    pub fn read(&self) -> Vec<EnrichedEntry> {
+        fs::read_dir(&self.dir).unwrap()
+            .map(|d| EnrichedEntry::from_dentry(&d.unwrap()))
+            .collect()
+    }
+
+    /// The entries sorted and grouped as the default listing has them.
+    pub fn listed(&self) -> Vec<String> {
+        let mut entries = self.read();
+        sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+        group::group_entries(&entries, GroupBy::DirsFirst).iter()
+            .flat_map(|g| g.entries.iter().map(|e| e.entry.get_name().to_string()))
+            .collect()
+    }
+
+    pub fn entry(&self, name: &str) -> EnrichedEntry {
+        EnrichedEntry::named(name, &self.dir.join(name))
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
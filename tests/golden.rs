@@ -0,0 +1,183 @@
+//! Renders a fixed fixture in each output format and compares the result
+//! with the golden files in tests/golden. After an intended change of the
+//! output, run the tests with UPDATE_GOLDEN=1 to rewrite them.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use my_ls::emit;
+use my_ls::entry::EnrichedEntry;
+use my_ls::environment::Environment;
+use my_ls::options::Options;
+use my_ls::render::{self, Renderer};
+use my_ls::sort;
+
+use common::Fixture;
+
+/// 2020-06-15 12:00:00 UTC, when the fixture files were last modified
+const MTIME: i64 = 1592222400;
+
+/// two weeks later, which the renderer takes for the current time
+const NOW: i64 = MTIME + 14 * 24 * 3600;
+
+/// Not a terminal, and none of the variables set.
+struct FakeEnvironment;
+
+impl Environment for FakeEnvironment {
+
+    fn now(&self) -> i64 {
+        NOW
+    }
+
+    fn terminal_width(&self) -> Option<usize> {
+        None
+    }
+
+    fn is_tty(&self) -> bool {
+        false
+    }
+
+    fn var(&self, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+/// The machine-readable outputs keep some state in statics, so only one
+/// test may render at a time.
+static RENDERING: Mutex<()> = Mutex::new(());
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    let files: [(&str, usize, u32); 5] = [
+        ("alpha.txt", 5, 0o644),
+        ("beta.rs", 120, 0o644),
+        ("gamma", 2048, 0o755),
+        ("delta file.md", 0, 0o600),
+        ("epsilon.tar.gz", 1_500_000, 0o444)
+    ];
+    for (name, size, mode) in files {
+        fixture.file(name, &vec![b'x'; size]);
+        fixture.fix_metadata(name, mode, MTIME);
+    }
+    fixture
+}
+
+/// Lists the fixture as the binary would with these arguments: sorted,
+/// not on a terminal, on a fake clock and in UTC.
+fn render(args: &[&str]) -> String {
+    let _guard = RENDERING.lock().unwrap_or_else(|e| e.into_inner());
+
+    // all the tests set the same time zone, so it does not matter which is first
+    env::set_var("TZ", "UTC");
+    let fixture = fixture();
+    let args: Vec<String> = ["my_ls"].iter().chain(args).map(|a| a.to_string()).collect();
+    let options = Options::from_args(&args).unwrap();
+    let mut entries: Vec<EnrichedEntry> = fs::read_dir(&fixture.dir).unwrap()
+        .map(|d| EnrichedEntry::from_dentry(&d.unwrap()))
+        .collect();
+    sort::sort_entries(&mut entries, &options.sort_keys, options.reverse, options.gnu);
+    let shown: Vec<&EnrichedEntry> = entries.iter().collect();
+
+    let mut renderer = Renderer::with_environment(&options, &fixture.dir, &FakeEnvironment);
+    renderer.prepare(&shown);
+    let fields = render::fields(&options);
+    let mut out = Vec::new();
+    emit::print_start(&mut out, options.output, &fields).unwrap();
+    match options.grid(&FakeEnvironment) {
+        Some(direction) => { renderer.print_grid(&mut out, &shown, direction).unwrap() }
+        None => {
+            for e in &shown {
+                renderer.print_entry(&mut out, e).unwrap();
+            }
+        }
+    }
+    emit::print_end(&mut out, options.output, &fields).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Compares the output with the golden file of that name, or with
+/// UPDATE_GOLDEN set, replaces the file with it.
+fn check(name: &str, output: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.txt"));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, output).unwrap();
+        return;
+    }
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Could not read '{}' ({err}); run with UPDATE_GOLDEN=1", path.display()));
+    assert_eq!(output, golden, "output differs from '{}'", path.display());
+}
+
+#[test]
+fn lines() {
+    check("lines", &render(&["-1"]));
+}
+
+#[test]
+fn lines_with_icons() {
+    check("lines_with_icons", &render(&["-1", "--icons", "always"]));
+}
+
+#[test]
+fn grid() {
+    check("grid", &render(&["-C", "--width", "50"]));
+}
+
+#[test]
+fn grid_across() {
+    check("grid_across", &render(&["-x", "--width", "50"]));
+}
+
+#[test]
+fn long() {
+    check("long", &render(&["-go"]));
+}
+
+#[test]
+fn long_gnu() {
+    check("long_gnu", &render(&["-go", "--gnu"]));
+}
+
+#[test]
+fn long_relative_time() {
+    check("long_relative_time", &render(&["-go", "--relative-time"]));
+}
+
+#[test]
+fn csv() {
+    check("csv", &render(&["--output", "csv", "--fields", "name,type,size,mtime,perms"]));
+}
+
+#[test]
+fn csv_unix_time() {
+    check("csv_unix_time", &render(&["--output", "csv", "--fields", "name,mtime", "--time-style", "unix"]));
+}
+
+#[test]
+fn json() {
+    check("json", &render(&["--output", "json", "--fields", "name,type,size,mtime,perms"]));
+}
+
+#[test]
+fn json_rfc3339_time() {
+    check("json_rfc3339_time", &render(&["--output", "json", "--fields", "name,mtime", "--time-style=rfc3339"]));
+}
+
+#[test]
+fn yaml() {
+    check("yaml", &render(&["--output", "yaml", "--fields", "name,type,size,mtime,perms"]));
+}
+
+#[test]
+fn porcelain() {
+    check("porcelain", &render(&["--porcelain"]));
+}
+
+#[test]
+fn porcelain_v2() {
+    check("porcelain_v2", &render(&["--porcelain=v2"]));
+}
@@ -0,0 +1,1039 @@
+//! Lists fixture directories with all kinds of entries, and checks how
+//! they are classified, ordered and rendered.
+
+mod common;
+
+use std::ffi::OsStr;
+use std::fs;
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use my_ls::category::{self, Category};
+use my_ls::clipboard;
+use my_ls::config;
+use my_ls::defaults;
+use my_ls::entry::{EnrichedEntry, EntryKind, ListingEntry};
+use my_ls::emit::Output;
+use my_ls::environment::Environment;
+use my_ls::filter::Filter;
+use my_ls::format;
+use my_ls::group::{self, GroupBy};
+use my_ls::glob;
+use my_ls::listing::DirListing;
+use my_ls::messages::Language;
+use my_ls::options::{Operand, Options};
+use my_ls::prefetch;
+use my_ls::render::Renderer;
+use my_ls::sort::{self, SortKey};
+use my_ls::stats::Stats;
+use my_ls::sys::{Dir, FileType};
+use my_ls::walk;
+
+use common::Fixture;
+
+fn options(args: &[&str]) -> Options {
+    let args: Vec<String> = ["my_ls"].iter().chain(args).map(|a| a.to_string()).collect();
+    Options::from_args(&args).unwrap()
+}
+
+/// What the binary prints with the arguments, run without any config
+/// file and in English.
+fn run(dir: &Path, args: &[&str]) -> String {
+    output(command(dir, args))
+}
+
+fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_my_ls"));
+    command.args(args).arg(dir).env("XDG_CONFIG_HOME", dir).env("LC_ALL", "C");
+    command
+}
+
+fn output(mut command: Command) -> String {
+    String::from_utf8(command.output().unwrap().stdout).unwrap()
+}
+
+/// A classic BPF instruction of a seccomp filter.
+#[repr(C)]
+struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+#[repr(C)]
+struct SockFprog {
+    len: u16,
+    filter: *const SockFilter,
+}
+
+extern "C" {
+    fn dup2(old: c_int, new: c_int) -> c_int;
+    fn prctl(option: c_int, arg2: c_ulong, arg3: c_ulong, arg4: c_ulong, arg5: c_ulong) -> c_int;
+}
+
+/// Makes every call that stats a path fail with EACCES in the command:
+/// `stat`, `lstat`, `newfstatat` and `statx` other than on a descriptor,
+/// and the O_PATH opens that precede an `fstat`. So whatever the command
+/// stats shows in what it prints, while the loader can still `fstat` the
+/// libraries it opens.
+#[cfg(target_arch = "x86_64")]
+fn without_stat(command: &mut Command) {
+    const LOAD: u16 = 0x20; // BPF_LD | BPF_W | BPF_ABS
+    const JEQ: u16 = 0x15; // BPF_JMP | BPF_JEQ | BPF_K
+    const JSET: u16 = 0x45; // BPF_JMP | BPF_JSET | BPF_K
+    const RET: u16 = 0x06; // BPF_RET | BPF_K
+    const ALLOW: u32 = 0x7fff_0000;
+    const EACCES: u32 = 0x0005_0000 | 13;
+    const AT_EMPTY_PATH: u32 = 0x1000;
+    const O_PATH: u32 = 0o10000000;
+    let op = |code, jt, jf, k| SockFilter { code, jt, jf, k };
+    // the call number is at offset 0, and the low half of argument i at 16 + 8 * i
+    let filter = [
+        op(LOAD, 0, 0, 0),
+        op(JEQ, 12, 0, 4),    // stat
+        op(JEQ, 11, 0, 6),    // lstat
+        op(JEQ, 3, 0, 262),   // newfstatat
+        op(JEQ, 4, 0, 332),   // statx
+        op(JEQ, 5, 0, 257),   // openat
+        op(RET, 0, 0, ALLOW),
+        op(LOAD, 0, 0, 40),   // newfstatat flags
+        op(JSET, 4, 5, AT_EMPTY_PATH),
+        op(LOAD, 0, 0, 32),   // statx flags
+        op(JSET, 2, 3, AT_EMPTY_PATH),
+        op(LOAD, 0, 0, 32),   // openat flags
+        op(JSET, 1, 0, O_PATH),
+        op(RET, 0, 0, ALLOW),
+        op(RET, 0, 0, EACCES),
+    ];
+    // SAFETY: only prctl runs between fork and exec, on a program that
+    // lives until the command is dropped
+    unsafe {
+        command.pre_exec(move || {
+            let program = SockFprog { len: filter.len() as u16, filter: filter.as_ptr() };
+            if prctl(38, 1, 0, 0, 0) != 0 || prctl(22, 2, &program as *const SockFprog as c_ulong, 0, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// What the renderer prints for the entries, in the given order.
+fn render(dir: &Path, entries: &[EnrichedEntry], args: &[&str]) -> String {
+    let options = options(args);
+    let mut renderer = Renderer::new(&options, dir);
+    let refs: Vec<&EnrichedEntry> = entries.iter().collect();
+    renderer.prepare(&refs);
+    let mut out = Vec::new();
+    for e in entries {
+        renderer.print_entry(&mut out, e).unwrap();
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn classifies_each_kind() {
+    let fixture = Fixture::with_all_kinds();
+    let kinds: Vec<(String, &str)> = {
+        let mut entries = fixture.read();
+        sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+        entries.iter().map(|e| (e.entry.get_name().to_string(), e.entry.type_name())).collect()
+    };
+    let expected = [
+        ("dangling", "symlink"),
+        ("empty", "file"),
+        ("link", "symlink"),
+        ("pipe", "pipe"),
+        ("regular", "file"),
+        ("socket", "socket"),
+        ("subdir", "directory")
+    ];
+    let kinds: Vec<(&str, &str)> = kinds.iter().map(|(n, k)| (n.as_str(), *k)).collect();
+    assert_eq!(kinds, expected);
+}
+
+#[test]
+fn tells_dangling_symlinks_apart() {
+    let fixture = Fixture::with_all_kinds();
+    let link = fixture.entry("link");
+    let dangling = fixture.entry("dangling");
+    assert!(!link.is_broken_symlink());
+    assert!(dangling.is_broken_symlink());
+    assert_eq!(dangling.entry.kind, EntryKind::Symlink { target: "nowhere".to_string() });
+}
+
+#[test]
+fn takes_only_devices_known_to_be_0_0_for_whiteouts() {
+    assert!(ListingEntry::new_char_device("deleted", Some(0)).is_whiteout());
+    assert!(!ListingEntry::new_char_device("null", Some(0x103)).is_whiteout());
+    assert!(!ListingEntry::new_char_device("unreadable", None).is_whiteout());
+    assert_eq!(ListingEntry::new_char_device("unreadable", None).kind, EntryKind::CharDevice { dev_id: None });
+}
+
+#[test]
+fn finds_empty_entries() {
+    let fixture = Fixture::with_all_kinds();
+    assert!(fixture.entry("empty").is_empty());
+    assert!(!fixture.entry("regular").is_empty());
+    assert!(fixture.entry("subdir").is_empty());
+}
+
+#[test]
+fn lists_directories_first_then_by_name() {
+    let fixture = Fixture::with_all_kinds();
+    fs::create_dir(fixture.dir.join("another")).unwrap();
+    assert_eq!(fixture.listed(),
+        ["another", "subdir", "dangling", "empty", "link", "pipe", "regular", "socket"]);
+}
+
+#[test]
+fn sorts_by_size_then_name() {
+    let fixture = Fixture::new();
+    fixture.file("b", b"12");
+    fixture.file("a", b"12");
+    fixture.file("c", b"1234");
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Size], false, false);
+    let names: Vec<&str> = entries.iter().map(|e| e.entry.get_name()).collect();
+    assert_eq!(names, ["c", "a", "b"]);
+}
+
+#[test]
+fn sorts_collated_as_by_bytes_in_the_c_locale() {
+    let fixture = Fixture::new();
+    for (name, content) in [("b", &b"12"[..]), ("a", b"12"), ("c", b"1234"), ("B", b""), ("_", b"")] {
+        fixture.file(name, content);
+    }
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Size], false, true);
+    let names: Vec<&str> = entries.iter().map(|e| e.entry.get_name()).collect();
+    assert_eq!(names, ["c", "a", "b", "B", "_"]);
+    sort::sort_entries(&mut entries, &[SortKey::Name], true, true);
+    let names: Vec<&str> = entries.iter().map(|e| e.entry.get_name()).collect();
+    assert_eq!(names, ["c", "b", "a", "_", "B"]);
+}
+
+#[test]
+fn sorts_names_regardless_of_case_and_by_version() {
+    let fixture = Fixture::new();
+    for name in ["b", "A", "C", "file-10.txt", "file-9.txt", "file-09a.txt", "file-1.txt"] {
+        fixture.file(name, b"");
+    }
+    let sorted = |keys: &[SortKey], collated: bool| {
+        let mut entries = fixture.read();
+        sort::sort_entries(&mut entries, keys, false, collated);
+        entries.iter().map(|e| e.entry.get_name().to_string()).collect::<Vec<_>>()
+    };
+    for collated in [false, true] {
+        assert_eq!(sorted(&[SortKey::NameIgnoringCase], collated),
+            ["A", "b", "C", "file-09a.txt", "file-1.txt", "file-10.txt", "file-9.txt"]);
+        assert_eq!(sorted(&[SortKey::Version], collated),
+            ["A", "C", "b", "file-1.txt", "file-9.txt", "file-09a.txt", "file-10.txt"]);
+    }
+    assert_eq!(sort::parse_sort_keys("version,iname"), Ok(vec![SortKey::Version, SortKey::NameIgnoringCase]));
+}
+
+#[test]
+fn sorts_directories_by_how_many_entries_they_have() {
+    let fixture = Fixture::new();
+    for (dir, count) in [("one", 1), ("none", 0), ("three", 3), ("also-one", 1)] {
+        fs::create_dir(fixture.dir.join(dir)).unwrap();
+        for i in 0..count {
+            fixture.file(format!("{dir}/{i}"), b"");
+        }
+    }
+    fixture.file("file", b"");
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Entries], false, false);
+    let names: Vec<&str> = entries.iter().map(|e| e.entry.get_name()).collect();
+    assert_eq!(names, ["three", "also-one", "one", "none", "file"]);
+}
+
+#[test]
+fn keeps_odd_names() {
+    let fixture = Fixture::new();
+    for name in ["-dash", "with space", "new\nline", "tab\there", "ünïcödé", "*"] {
+        fixture.file(name, b"");
+    }
+    assert_eq!(fixture.listed(), ["*", "-dash", "new\nline", "tab\there", "with space", "ünïcödé"]);
+}
+
+#[test]
+fn shows_names_that_are_not_utf8_as_unknown() {
+    let fixture = Fixture::new();
+    fixture.file(OsStr::from_bytes(b"bad\xffname"), b"");
+    let entries = fixture.read();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].entry.kind, EntryKind::Unknown);
+    assert_eq!(entries[0].entry.get_name(), "???");
+}
+
+/// Undoes `format::escape_name()`.
+fn unescape(name: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match rest[0] {
+            b't' => { bytes.push(b'\t') }
+            b'n' => { bytes.push(b'\n') }
+            b'x' => {
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&rest[1..3]).unwrap(), 16).unwrap());
+                rest = &rest[2..];
+            }
+            other => { bytes.push(other) }
+        }
+        rest = &rest[1..];
+    }
+    bytes
+}
+
+#[test]
+fn escapes_names_so_that_they_can_be_told_back() {
+    let fixture = Fixture::new();
+    let names: [&[u8]; 5] = [b"bad\xffname", b"back\\slash", b"new\nline", b"plain", b"tab\there"];
+    for name in names {
+        fixture.file(OsStr::from_bytes(name), b"");
+    }
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+
+    let porcelain = render(&fixture.dir, &entries, &["--porcelain=v2"]);
+    let fields: Vec<(&str, &str)> = porcelain.lines()
+        .map(|line| line.split('\t').skip(4).collect::<Vec<_>>())
+        .map(|f| (f[0], f[1]))
+        .collect();
+    assert_eq!(fields, [("1", "bad\\xffname"), ("1", "back\\\\slash"), ("1", "new\\nline"), ("0", "plain"), ("1", "tab\\there")]);
+    assert_eq!(fields.iter().map(|(_, name)| unescape(name)).collect::<Vec<_>>(), names);
+
+    // version 1 is as it always was
+    let porcelain = render(&fixture.dir, &entries, &["--porcelain"]);
+    let names: Vec<&str> = porcelain.lines().map(|line| line.split('\t').nth(4).unwrap()).collect();
+    assert_eq!(names, ["???", "back\\\\slash", "new\\nline", "plain", "tab\\there"]);
+
+    let csv = render(&fixture.dir, &entries, &["--output", "csv", "--fields", "name", "--escape-names"]);
+    assert_eq!(csv, "bad\\xffname,1\nback\\\\slash,1\nnew\\nline,1\nplain,0\ntab\\there,1\n");
+}
+
+#[test]
+fn takes_the_porcelain_version_from_the_next_argument_only_if_it_is_one() {
+    let porcelain = |args: &[&str]| {
+        let options = options(args);
+        (options.output, options.paths.iter().map(|p| p.path.clone()).collect::<Vec<_>>())
+    };
+    assert_eq!(porcelain(&["--porcelain", "v2"]), (Output::Porcelain(2), vec![".".to_string()]));
+    assert_eq!(porcelain(&["--porcelain", "v1", "dir"]), (Output::Porcelain(1), vec!["dir".to_string()]));
+    assert_eq!(porcelain(&["--porcelain", "dir"]), (Output::Porcelain(1), vec!["dir".to_string()]));
+    assert_eq!(porcelain(&["--porcelain=v2", "v1"]), (Output::Porcelain(2), vec!["v1".to_string()]));
+}
+
+#[test]
+fn renders_names_and_targets() {
+    let fixture = Fixture::with_all_kinds();
+    let entries = vec![fixture.entry("regular"), fixture.entry("dangling"), fixture.entry("subdir")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--fields", "name"]);
+    assert_eq!(text, "regular\ndangling -> nowhere\nsubdir\n");
+}
+
+#[test]
+fn aligns_the_columns_by_how_wide_the_names_are_on_screen() {
+    let fixture = Fixture::new();
+    fixture.file("日本", b"");
+    fixture.file("abc", b"12");
+    let entries = vec![fixture.entry("日本"), fixture.entry("abc")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--fields", "name,size"]);
+    assert_eq!(text, "日本 0\nabc  2\n");
+}
+
+#[test]
+fn renders_paths_relative_to_a_directory() {
+    let fixture = Fixture::new();
+    fs::create_dir_all(fixture.dir.join("src/bin")).unwrap();
+    fixture.file("src/lib.rs", b"");
+    fixture.file("README", b"");
+    let entries = vec![EnrichedEntry::from_path(&fixture.dir.join("src/lib.rs")), fixture.entry("README")];
+    let base = fixture.dir.join("src/bin");
+    let text = render(&fixture.dir, &entries, &["--gnu", "--fields", "name", "--relative-to", base.to_str().unwrap()]);
+    assert_eq!(text, "../lib.rs\n../../README\n");
+}
+
+#[test]
+fn renders_exact_sizes_with_separators() {
+    let fixture = Fixture::new();
+    fixture.file("big", &vec![0; 1234567]);
+    let entries = vec![fixture.entry("big")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--bytes", "--fields", "size,name"]);
+    assert_eq!(text, "1,234,567 big\n");
+}
+
+#[test]
+fn shows_allocated_blocks_before_the_names() {
+    let fixture = Fixture::new();
+    fixture.file("empty", b"");
+    fixture.file("full", &vec![1; 100_000]);
+    let entries = vec![fixture.entry("empty"), fixture.entry("full")];
+    let kilobytes = fs::metadata(fixture.dir.join("full")).unwrap().blocks().div_ceil(2);
+    let width = kilobytes.to_string().len();
+    let text = render(&fixture.dir, &entries, &["--gnu", "-s"]);
+    assert_eq!(text, format!("{:>width$} empty\n{kilobytes} full\n", 0));
+
+    let options = options(&["--gnu", "-s"]);
+    let refs: Vec<&EnrichedEntry> = entries.iter().collect();
+    let mut out = Vec::new();
+    Renderer::new(&options, &fixture.dir).print_total(&mut out, &refs).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), format!("total {kilobytes}\n"));
+}
+
+#[test]
+fn renders_sizes_in_a_fixed_unit() {
+    let fixture = Fixture::new();
+    fixture.file("empty", b"");
+    fixture.file("small", &[0; 100]);
+    fixture.file("big", &vec![0; 3 * 1024 * 1024 + 1]);
+    let entries = vec![fixture.entry("empty"), fixture.entry("small"), fixture.entry("big")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--size-unit", "M", "--fields", "size,name"]);
+    assert_eq!(text, "   0 empty\n0.1M small\n3.1M big\n");
+    let text = render(&fixture.dir, &entries, &["--gnu", "--size-unit=K", "--fields", "size,name"]);
+    assert_eq!(text, "    0 empty\n 0.1K small\n3073K big\n");
+}
+
+#[test]
+fn draws_bars_of_the_sizes() {
+    let fixture = Fixture::new();
+    fixture.file("half", &[0; 500]);
+    fixture.file("full", &[0; 1000]);
+    fs::create_dir(fixture.dir.join("dir")).unwrap();
+    let entries = vec![fixture.entry("half"), fixture.entry("full"), fixture.entry("dir")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--bar", "--fields", "size,name"]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], " 500 ████     half");
+    assert_eq!(lines[1], "1000 ████████ full");
+    assert!(lines[2].ends_with("          dir"), "{:?}", lines[2]);
+}
+
+#[test]
+fn shows_what_symlinks_point_to() {
+    let fixture = Fixture::new();
+    fixture.file("target", b"12345");
+    fixture.fix_metadata("target", 0o640, 1592222400);
+    std::os::unix::fs::symlink("target", fixture.dir.join("link")).unwrap();
+    std::os::unix::fs::symlink("nowhere", fixture.dir.join("dangling")).unwrap();
+    let entries = vec![fixture.entry("link"), fixture.entry("dangling")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "-l", "--link-target-metadata", "--fields", "name"]);
+    assert_eq!(text, "link -> target (-rw-r----- 5 Jun 15  2020)\ndangling -> nowhere\n");
+}
+
+#[test]
+fn marks_ids_without_names() {
+    let fixture = Fixture::new();
+    fixture.file("orphan", b"");
+
+    // only root can give files away
+    if std::os::unix::fs::chown(fixture.dir.join("orphan"), Some(54321), Some(54321)).is_err() {
+        return;
+    }
+    let entries = vec![fixture.entry("orphan")];
+    assert_eq!(render(&fixture.dir, &entries, &["--fields", "owner,group,name"]), "#54321 #54321 orphan\n");
+    assert_eq!(render(&fixture.dir, &entries, &["--gnu", "--fields", "owner,group,name"]), "54321 54321 orphan\n");
+}
+
+#[test]
+fn points_out_entries_unlike_the_others() {
+    let fixture = Fixture::new();
+    for (name, mode) in [("a", 0o644), ("b", 0o644), ("c", 0o644), ("odd", 0o600)] {
+        fixture.file(name, b"");
+        fixture.fix_metadata(name, mode, 1592222400);
+    }
+    let entries: Vec<EnrichedEntry> = ["a", "b", "c", "odd"].iter().map(|name| fixture.entry(name)).collect();
+    let text = render(&fixture.dir, &entries, &["--anomalies", "--fields", "name"]);
+    assert_eq!(text, "a\nb\nc\nodd [unusual permissions]\n");
+
+    // only root can give files away
+    if std::os::unix::fs::chown(fixture.dir.join("c"), Some(54321), None).is_ok() {
+        let entries: Vec<EnrichedEntry> = ["a", "b", "c", "odd"].iter().map(|name| fixture.entry(name)).collect();
+        let text = render(&fixture.dir, &entries, &["--anomalies", "--fields", "name"]);
+        assert_eq!(text, "a\nb\nc [unusual owner]\nodd [unusual permissions]\n");
+    }
+}
+
+#[test]
+fn lines_up_the_fields_after_colored_names() {
+    let fixture = Fixture::new();
+    for (name, mode) in [("a", 0o644), ("b", 0o644), ("odd", 0o600)] {
+        fixture.file(name, b"");
+        fixture.fix_metadata(name, mode, 1592222400);
+    }
+    let entries: Vec<EnrichedEntry> = ["a", "b", "odd"].iter().map(|name| fixture.entry(name)).collect();
+    let text = render(&fixture.dir, &entries, &["--anomalies", "--color=always", "--fields", "name,links"]);
+
+    // without the escape sequences, the link counts are in one column
+    let mut plain = String::new();
+    let mut in_escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => { in_escape = true }
+            'm' if in_escape => { in_escape = false }
+            c if !in_escape => { plain.push(c) }
+            _ => { }
+        }
+    }
+    assert_eq!(plain, format!("{:26}1\n{:26}1\nodd [unusual permissions] 1\n", "a", "b"));
+}
+
+#[test]
+fn highlights_link_counts_of_files_with_more_names() {
+    let fixture = Fixture::new();
+    fixture.file("alone", b"");
+    fixture.file("shared", b"");
+    fs::hard_link(fixture.dir.join("shared"), fixture.dir.join("alias")).unwrap();
+    let entries = vec![fixture.entry("alone"), fixture.entry("shared")];
+    let text = render(&fixture.dir, &entries, &["--color=always", "--fields", "links,type"]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "1 file");
+    assert!(lines[1].starts_with("\x1b[") && lines[1].ends_with("2\x1b[0m file"), "{:?}", lines[1]);
+}
+
+#[test]
+fn leaves_out_owners_that_are_the_current_user() {
+    let fixture = Fixture::new();
+    fixture.file("mine", b"");
+    let entries = vec![fixture.entry("mine")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--smart-owner", "--fields", "owner,group,name"]);
+    assert_eq!(text, "mine\n");
+}
+
+#[test]
+fn tells_how_old_pipes_and_sockets_are() {
+    let fixture = Fixture::with_all_kinds();
+    let entries = vec![fixture.entry("pipe"), fixture.entry("socket"), fixture.entry("regular")];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--ipc-age", "--fields", "name"]);
+    assert_eq!(text, "pipe (modified just now)\nsocket (modified just now)\nregular\n");
+}
+
+#[test]
+fn tells_why_entries_could_not_be_read() {
+    let fixture = Fixture::new();
+    let entries = vec![EnrichedEntry::unreadable(&std::io::ErrorKind::PermissionDenied.into())];
+    let text = render(&fixture.dir, &entries, &["--gnu", "--fields", "name"]);
+    assert_eq!(text, "??? (access denied)\n");
+    let text = render(&fixture.dir, &entries, &["--gnu", "--show-errors=none", "--fields", "name"]);
+    assert_eq!(text, "???\n");
+}
+
+#[test]
+fn marks_entries_deleted_while_listed() {
+    let fixture = Fixture::new();
+    fixture.file("kept", b"");
+    fixture.file("gone", b"");
+    let dentries: Vec<fs::DirEntry> = fs::read_dir(&fixture.dir).unwrap().map(Result::unwrap).collect();
+    fs::remove_file(fixture.dir.join("gone")).unwrap();
+    let mut entries: Vec<EnrichedEntry> = dentries.iter().map(EnrichedEntry::from_dentry).collect();
+    sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+    assert_eq!(entries.iter().map(|e| e.vanished).collect::<Vec<_>>(), [true, false]);
+    let text = render(&fixture.dir, &entries, &["--gnu", "--fields", "name"]);
+    assert_eq!(text, "gone (deleted during listing)\nkept\n");
+}
+
+#[test]
+fn walks_the_tree_without_following_symlinks() {
+    let fixture = Fixture::new();
+    fixture.file("top", b"");
+    fs::create_dir_all(fixture.dir.join("a/b")).unwrap();
+    fixture.file("a/b/deep", b"12");
+    std::os::unix::fs::symlink("a", fixture.dir.join("link")).unwrap();
+    let mut seen = Vec::new();
+    walk::walk(&fixture.dir, true, &[], &mut |e| {
+        seen.push((e.path.strip_prefix(&fixture.dir).unwrap().display().to_string(), e.entry.type_name()));
+    });
+    seen.sort();
+    let seen: Vec<(&str, &str)> = seen.iter().map(|(p, k)| (p.as_str(), *k)).collect();
+    assert_eq!(seen, [("a", "directory"), ("a/b", "directory"), ("a/b/deep", "file"), ("link", "symlink"), ("top", "file")]);
+}
+
+#[test]
+fn lists_a_directory_given_by_descriptor() {
+    let fixture = Fixture::new();
+    fs::create_dir(fixture.dir.join("sub")).unwrap();
+    fixture.file("sub/inside", b"");
+    let dir = fs::File::open(&fixture.dir).unwrap();
+    let operand = format!("fd:{}", dir.as_raw_fd());
+    let options = options(&[&operand, "--", "fd:4"]);
+    assert_eq!(options.paths, [Operand { path: operand.clone(), fd: Some(dir.as_raw_fd()) }, Operand::path("fd:4")]);
+    let mut seen = Vec::new();
+    walk::walk_opened(Path::new(&operand), options.paths[0].open(), true, &[], &mut |e| seen.push(e.path.clone()));
+    seen.sort();
+    assert_eq!(seen, [Path::new(&operand).join("sub"), Path::new(&operand).join("sub/inside")]);
+
+    // the child gets the directory as descriptor 7, and nothing else of it
+    let mut command = Command::new(env!("CARGO_BIN_EXE_my_ls"));
+    command.args(["--no-local-config", "-R", "fd:7"]).current_dir("/").env("XDG_CONFIG_HOME", &fixture.dir);
+    let fd = dir.as_raw_fd();
+    // SAFETY: only dup2() runs between fork and exec
+    unsafe {
+        command.pre_exec(move || if dup2(fd, 7) < 0 { Err(std::io::Error::last_os_error()) } else { Ok(()) });
+    }
+    assert_eq!(output(command), "fd:7:\nsub\n1 entry, 0 bytes in files\n\nfd:7/sub:\ninside\n1 entry, 0 bytes in files\n\nTotal: 2 entries in 2 directories, 0 bytes in files\n");
+
+    let args = ["my_ls", "fd:three"].map(String::from);
+    assert_eq!(Options::from_args(&args).err().unwrap(), "Invalid file descriptor 'fd:three'");
+}
+
+#[test]
+fn reports_on_trees_have_no_totals_footer() {
+    let fixture = Fixture::new();
+    fs::create_dir(fixture.dir.join("sub")).unwrap();
+    fixture.file("sub/notes.txt", b"12345");
+    fixture.file("copy.txt", b"12345");
+    for report in [&["--usage"][..], &["--largest", "1"], &["--dupes"]] {
+        let output = run(&fixture.dir, &[report, &["-R", "--no-local-config"]].concat());
+        assert!(!output.is_empty() && !output.contains("Total:"), "{report:?} printed {output:?}");
+    }
+    assert!(run(&fixture.dir, &["-R", "--no-local-config"]).contains("Total: 3 entries in 2 directories"));
+}
+
+#[test]
+fn reads_directories_relative_to_the_open_parent() {
+    let fixture = Fixture::new();
+    fs::create_dir_all(fixture.dir.join("tree/sub")).unwrap();
+    fixture.file("tree/sub/deep", b"");
+    std::os::unix::fs::symlink("sub", fixture.dir.join("tree/link")).unwrap();
+    let tree = Dir::open(&fixture.dir.join("tree")).unwrap();
+
+    // the directory can be read again, and tells the types of its entries
+    for _ in 0..2 {
+        let mut entries: Vec<(String, Option<FileType>)> = tree.entries().unwrap()
+            .map(|d| d.unwrap())
+            .map(|d| (d.name.to_string_lossy().into_owned(), d.file_type))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(entries, [("link".to_string(), Some(FileType::Symlink)), ("sub".to_string(), Some(FileType::Directory))]);
+    }
+
+    // moving the tree does not move what is opened in it, nor are symlinks
+    // followed unless asked to
+    fs::rename(fixture.dir.join("tree"), fixture.dir.join("moved")).unwrap();
+    assert_eq!(tree.open_at(OsStr::new("sub")).unwrap().names().unwrap(), ["deep"]);
+    assert!(tree.open_at(OsStr::new("link")).is_err());
+    assert_eq!(tree.open_following_at(OsStr::new("link")).unwrap().names().unwrap(), ["deep"]);
+    assert_eq!(tree.read_link_at(OsStr::new("link")).unwrap(), Path::new("sub"));
+}
+
+#[test]
+fn reads_the_entries_of_a_listing_one_by_one() {
+    let fixture = Fixture::with_all_kinds();
+    let listing = DirListing::open(&fixture.dir).unwrap();
+    let mut names: Vec<(String, bool)> = listing.iter().unwrap()
+        .map(|e| e.unwrap())
+        .map(|e| (e.entry.get_name().to_string(), e.metadata.is_some()))
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 7);
+    assert!(names.iter().all(|(_, metadata)| !metadata));
+
+    let listing = listing.with_metadata(true);
+    let subdir = listing.iter().unwrap().map(|e| e.unwrap()).find(|e| e.entry.get_name() == "subdir").unwrap();
+    assert!(subdir.entry.is_directory() && subdir.metadata.is_some_and(|m| m.is_dir()));
+    assert_eq!(subdir.path, fixture.dir.join("subdir"));
+}
+
+#[test]
+fn looks_entries_up_in_the_directory_they_were_read_from() {
+    let fixture = Fixture::new();
+    fs::create_dir_all(fixture.dir.join("tree/sub")).unwrap();
+    fixture.file("tree/file", b"some text");
+    std::os::unix::fs::symlink("file", fixture.dir.join("tree/link")).unwrap();
+    std::os::unix::fs::symlink("nowhere", fixture.dir.join("tree/dangling")).unwrap();
+    let tree = Dir::open(&fixture.dir.join("tree")).unwrap();
+    let entry = |name: &str| EnrichedEntry::in_dir(&tree, &fixture.dir.join("tree"), OsStr::new(name));
+    let (file, link, dangling, sub) = (entry("file"), entry("link"), entry("dangling"), entry("sub"));
+
+    let on_disk = fs::symlink_metadata(fixture.dir.join("tree/link")).unwrap();
+    let metadata = link.metadata.as_ref().unwrap();
+    assert_eq!((metadata.ino(), metadata.size(), metadata.mode()), (on_disk.ino(), on_disk.size(), on_disk.mode()));
+
+    // the paths the entries were found at are gone
+    fs::rename(fixture.dir.join("tree"), fixture.dir.join("moved")).unwrap();
+    assert!(!file.is_empty() && sub.is_empty());
+    assert!(!link.is_broken_symlink() && dangling.is_broken_symlink());
+}
+
+#[test]
+fn expands_wildcards_the_shell_left_alone() {
+    let fixture = Fixture::new();
+    for name in ["b.txt", "a.txt", ".hidden.txt", "c.rs"] {
+        fixture.file(name, b"");
+    }
+    fs::create_dir(fixture.dir.join("sub")).unwrap();
+    fixture.file("sub/d.txt", b"");
+    let dir = fixture.dir.display().to_string();
+    let expand = |pattern: &str| -> Vec<String> {
+        glob::expand_path(&format!("{dir}/{pattern}")).iter().map(|p| p[dir.len() + 1..].to_string()).collect()
+    };
+    assert_eq!(expand("*.txt"), ["a.txt", "b.txt"]);
+    assert_eq!(expand(".*.txt"), [".hidden.txt"]);
+    assert_eq!(expand("*/*.txt"), ["sub/d.txt"]);
+    assert_eq!(expand("*/"), ["sub/"]);
+    assert_eq!(expand("*.md"), ["*.md"]);
+}
+
+#[test]
+fn classifies_files_by_extension() {
+    let fixture = Fixture::new();
+    for name in ["main.rs", "Photo.JPG", "notes.md", "backup.tar.gz", "README"] {
+        fixture.file(name, b"");
+    }
+    fs::create_dir(fixture.dir.join("dir.rs")).unwrap();
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+    let categories: Vec<Option<Category>> = entries.iter().map(category::of).collect();
+    assert_eq!(categories, [Some(Category::Image), None, Some(Category::Archive), None, Some(Category::Code),
+        Some(Category::Document)]);
+
+    let filter = Filter::parse_category("code,image").unwrap();
+    let names: Vec<&str> = entries.iter().filter(|e| filter.matches(e)).map(|e| e.entry.get_name()).collect();
+    assert_eq!(names, ["Photo.JPG", "main.rs"]);
+    assert!(Filter::parse_category("code,spreadsheet").is_err());
+
+    let titles: Vec<String> = group::group_entries(&entries, GroupBy::Category).into_iter().map(|g| g.title).collect();
+    assert_eq!(titles, ["Code", "Documents", "Images", "Archives", "Other"]);
+}
+
+#[test]
+fn mixes_directories_with_files_on_request() {
+    let fixture = Fixture::new();
+    fixture.file("a", b"");
+    fs::create_dir(fixture.dir.join("b")).unwrap();
+    fixture.file("c", b"");
+    let mut entries = fixture.read();
+    sort::sort_entries(&mut entries, &[SortKey::Name], false, false);
+    let sections = |args: &[&str]| -> Vec<Vec<String>> {
+        group::group_entries(&entries, options(args).grouping()).into_iter()
+            .map(|g| g.entries.iter().map(|e| e.entry.get_name().to_string()).collect())
+            .collect()
+    };
+    assert_eq!(sections(&[]), [vec!["b"], vec!["a", "c"]]);
+    assert_eq!(sections(&["--no-dirs-first"]), [vec!["a", "b", "c"]]);
+}
+
+#[test]
+fn sums_up_the_categories() {
+    let fixture = Fixture::new();
+    fixture.file("main.rs", &vec![b'x'; 2048]);
+    fixture.file("lib.rs", b"");
+    fixture.file("photo.png", b"");
+    fixture.file("README", b"");
+    fs::create_dir(fixture.dir.join("src")).unwrap();
+    let entries = fixture.read();
+    let refs: Vec<&EnrichedEntry> = entries.iter().collect();
+    let mut out = Vec::new();
+    category::print_summary(&mut out, &refs).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "code: 2 (2.0K)  images: 1  other: 2\n");
+}
+
+#[test]
+fn reads_directories_ahead_on_workers() {
+    let fixture = Fixture::new();
+    for dir in ["a", "b"] {
+        fs::create_dir(fixture.dir.join(dir)).unwrap();
+        fixture.file(format!("{dir}/{dir}1"), b"");
+    }
+    prefetch::start(2, |path, dir| {
+        Ok(dir.entries()?.map(|d| EnrichedEntry::in_dir(&dir, path, &d.unwrap().name)).collect())
+    });
+    for dir in ["a", "b"] {
+        let path = fixture.dir.join(dir);
+        assert!(prefetch::request(&path, Dir::open(&path).unwrap()));
+    }
+    assert!(prefetch::take(&fixture.dir).is_none());
+    for dir in ["b", "a"] {
+        let listing = prefetch::take(&fixture.dir.join(dir)).unwrap().unwrap();
+        let names: Vec<&str> = listing.iter().map(|e| e.entry.get_name()).collect();
+        assert_eq!(names, [format!("{dir}1")]);
+    }
+    assert!(prefetch::take(&fixture.dir.join("a")).is_none());
+}
+
+#[test]
+fn encodes_the_clipboard_sequence() {
+    assert_eq!(clipboard::osc52("/tmp/a"), "\x1b]52;c;L3RtcC9h\x07");
+    assert_eq!(clipboard::osc52("/tmp/ab\n/x"), "\x1b]52;c;L3RtcC9hYgoveA==\x07");
+}
+
+#[test]
+fn takes_default_options_from_the_profiles_that_apply() {
+    let config = config::parse(concat!(
+        "[defaults]\nhidden = \"almost-all\"\nsort = \"name\"\n",
+        "[[profile]]\ndirectory = \"~/Downloads\"\noutput = \"long\"\nsort = \"time\"\n",
+        "[[profile]]\nhost = \"build-*\"\nhidden = \"none\"\nargs = [\"--no-icons\"]\n"
+    )).unwrap();
+    let home = Path::new("/home/someone");
+    let args = |host: &str, dir: &str| defaults::args_from(&config, Some(host), Path::new(dir), Some(home)).unwrap();
+    assert_eq!(args("desktop", "/home/someone/Downloads"), ["--sort=time", "--almost-all", "--long"]);
+    assert_eq!(args("desktop", "/home/someone"), ["--sort=name", "--almost-all"]);
+    assert_eq!(args("build-1", "/srv"), ["--sort=name", "--no-icons"]);
+
+    let config = config::parse("[defaults]\ncolour = \"never\"\n").unwrap();
+    let err = defaults::args_from(&config, None, Path::new("/"), None).unwrap_err();
+    assert_eq!(err, "[defaults]: Unknown setting 'colour'");
+}
+
+#[test]
+fn takes_options_from_the_nearest_local_config_file() {
+    let fixture = Fixture::new();
+    fixture.file(".my_ls.toml", b"sort = \"time\"\nargs = [\"--long\"]\n");
+    fs::create_dir(fixture.dir.join("media")).unwrap();
+    let (path, args) = defaults::local_args(&fixture.dir.join("media")).unwrap().unwrap();
+    assert_eq!(path, fixture.dir.canonicalize().unwrap().join(".my_ls.toml"));
+    assert_eq!(args, ["--sort=time", "--long"]);
+
+    let config = config::parse("args = [\"--exec=rm {}\"]\n").unwrap();
+    assert_eq!(defaults::local_args_from(&config).unwrap_err(), "Option '--exec=rm {}' is not allowed here");
+}
+
+#[test]
+fn takes_only_display_options_from_local_config_files() {
+    let local_args = |args: &str| {
+        let config = config::parse(&format!("args = [{args}]\n")).unwrap();
+        defaults::local_args_from(&config)
+    };
+    assert_eq!(local_args("\"-lh\", \"--time-style\", \"iso\", \"--width=80\"").unwrap(),
+        ["-lh", "--time-style", "iso", "--width=80"]);
+
+    assert_eq!(local_args("\"--files-from=list\"").unwrap_err(), "Option '--files-from=list' is not allowed here");
+    assert_eq!(local_args("\"--recursive\"").unwrap_err(), "Option '--recursive' is not allowed here");
+    assert_eq!(local_args("\"-lR\"").unwrap_err(), "Option '-R' is not allowed here");
+    assert_eq!(local_args("\"--long\", \"/etc\"").unwrap_err(), "Operand '/etc' is not allowed here");
+    assert_eq!(local_args("\"--\", \"-l\"").unwrap_err(), "Option '--' is not allowed here");
+    assert_eq!(local_args("\"-\"").unwrap_err(), "Operand '-' is not allowed here");
+}
+
+/// Only has the locale variables it is made with.
+struct Locale(&'static [(&'static str, &'static str)]);
+
+impl Environment for Locale {
+
+    fn now(&self) -> i64 {
+        0
+    }
+
+    fn terminal_width(&self) -> Option<usize> {
+        None
+    }
+
+    fn is_tty(&self) -> bool {
+        false
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, value)| value.to_string())
+    }
+}
+
+#[test]
+fn speaks_the_language_of_the_locale() {
+    assert_eq!(Language::detect(&Locale(&[("LANG", "cs_CZ.UTF-8")])), Language::Czech);
+    assert_eq!(Language::detect(&Locale(&[("LANG", "cs_CZ.UTF-8"), ("LC_ALL", "C")])), Language::English);
+    assert_eq!(Language::detect(&Locale(&[("LC_MESSAGES", "cs"), ("LANG", "de_DE")])), Language::Czech);
+    assert_eq!(Language::detect(&Locale(&[])), Language::English);
+
+    let files = [1, 3, 5].map(|n| Language::Czech.count("{} file|{} files", n));
+    assert_eq!(files, ["1 soubor", "3 soubory", "5 souborů"]);
+    assert_eq!(Language::English.count("{} file|{} files", 3), "3 files");
+    assert_eq!(Language::Czech.tr("Directories"), "Adresáře");
+    assert_eq!(Language::Czech.tr("not in the catalog"), "not in the catalog");
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn lists_without_statting_the_entries_by_default() {
+    let fixture = Fixture::new();
+    fixture.file("notes.txt", b"notes");
+    fs::create_dir_all(fixture.dir.join("project/.git")).unwrap();
+    symlink("notes.txt", fixture.dir.join("link")).unwrap();
+
+    let args = ["--no-local-config"];
+    let mut blind = command(&fixture.dir, &args);
+    without_stat(&mut blind);
+    assert_eq!(output(blind), run(&fixture.dir, &args));
+
+    // the long listing does need the metadata, so the filter is in place
+    let mut blind = command(&fixture.dir, &["-l"]);
+    without_stat(&mut blind);
+    assert_ne!(output(blind), run(&fixture.dir, &["-l"]));
+}
+
+#[test]
+fn counts_the_total_in_the_units_of_the_block_sizes() {
+    let fixture = Fixture::new();
+    fixture.file("small", &[0; 5000]);
+    fixture.file("large", &[0; 20000]);
+
+    let blocks = |name: &str| fs::symlink_metadata(fixture.dir.join(name)).unwrap().blocks() * 512;
+    let total = blocks("small") + blocks("large");
+    let expected = format!("total {}\n{:>4} large\n{:>4} small\n",
+        format::human_size(total), format::human_size(blocks("large")), format::human_size(blocks("small")));
+    assert_eq!(run(&fixture.dir, &["--no-local-config", "-s"]), expected);
+}
+
+#[test]
+fn reports_in_the_language_of_the_locale() {
+    let fixture = Fixture::new();
+    fixture.file("notes.txt", b"notes");
+    fixture.file("copy.txt", b"notes");
+
+    let czech = |args: &[&str]| {
+        let mut command = command(&fixture.dir, args);
+        command.env("LC_ALL", "cs_CZ.UTF-8");
+        output(command)
+    };
+    let stat = czech(&["--no-local-config", "--stat"]);
+    assert!(stat.starts_with("Položky:          2\n"), "{stat}");
+    assert!(stat.contains("\nCelková velikost: 10 (10 bajtů)\n"), "{stat}");
+    assert!(stat.contains("\nPřípony:\n  .txt 2\n"), "{stat}");
+    assert!(czech(&["--no-local-config", "--usage"]).ends_with("\nCelkem 10 2 soubory\n"));
+    assert!(czech(&["--no-local-config", "--dupes"]).starts_with("2 soubory, každý 5:\n"));
+}
+
+#[test]
+fn shows_other_times_as_the_time_column_does() {
+    let fixture = Fixture::new();
+    fixture.file("notes.txt", b"notes");
+    fixture.fix_metadata("notes.txt", 0o644, 0);
+
+    // on the clock of the environment, which is at the epoch
+    let options = options(&["--stat", "--relative-time"]);
+    let renderer = Renderer::with_environment(&options, &fixture.dir, &Locale(&[]));
+    let entries = fixture.read();
+    let mut out = Vec::new();
+    Stats::collect(&entries.iter().collect::<Vec<_>>()).print(&mut out, renderer.cell_format()).unwrap();
+    assert!(String::from_utf8(out).unwrap().contains(" notes.txt (just now)\n"));
+
+    symlink("notes.txt", fixture.dir.join("link")).unwrap();
+    let listed = render(&fixture.dir, &[fixture.entry("link")], &["-l", "--link-target-metadata", "--time-style", "unix"]);
+    assert!(listed.ends_with(" link -> notes.txt (-rw-r--r-- 5 0)\n"), "{listed}");
+}
+
+#[test]
+fn goes_into_the_subdirectories_outside_the_window() {
+    let fixture = Fixture::new();
+    for dir in ["a", "b"] {
+        fs::create_dir(fixture.dir.join(dir)).unwrap();
+        fs::write(fixture.dir.join(dir).join("notes.txt"), b"notes").unwrap();
+    }
+    for args in [&["-R", "--head", "1"][..], &["-U", "-R", "--head", "1"]] {
+        let listed = run(&fixture.dir, args);
+        for dir in ["a", "b"] {
+            assert!(listed.contains(&format!("\n{}:\nnotes.txt\n", fixture.dir.join(dir).display())), "{args:?}: {listed}");
+        }
+    }
+}
+
+#[test]
+fn reports_on_trees_fail_on_what_they_cannot_read() {
+    let fixture = Fixture::new();
+    fixture.file("empty", b"");
+    let usage = run(&fixture.dir, &["--usage"]);
+    assert!(usage.lines().all(|line| !line.ends_with(' ')), "{usage:?}");
+
+    for args in [&["--usage"][..], &["--largest", "1"], &["--dupes"]] {
+        let status = command(&fixture.dir.join("gone"), args).output().unwrap().status;
+        assert_eq!(status.code(), Some(1), "{args:?}");
+    }
+}
+
+#[test]
+fn formats_only_listings() {
+    let args = |args: &[&str]| {
+        let args: Vec<String> = ["my_ls"].iter().chain(args).map(|a| a.to_string()).collect();
+        Options::from_args(&args)
+    };
+    for (mode, value) in [("--usage", None), ("--largest", Some("3")), ("--dupes", None), ("--stat", None),
+                          ("--exec", Some("echo {}")), ("--exec-batch", Some("echo"))] {
+        for output in ["json", "csv", "yaml", "dot"] {
+            let result = args(&["--output", output, mode].into_iter().chain(value).collect::<Vec<_>>());
+            assert_eq!(result.err(), Some(format!("Option '--output' cannot be used with '{mode}'")));
+        }
+        assert!(args(&[mode].into_iter().chain(value).collect::<Vec<_>>()).is_ok(), "{mode}");
+    }
+}
+
+#[test]
+fn gives_up_on_large_directories_before_listing_any_of_them() {
+    let fixture = Fixture::new();
+    for name in ["a", "b", "c", ".hidden"] {
+        fixture.file(name, b"");
+    }
+    for sorted in [true, false] {
+        let args: &[&str] = if sorted { &["--max-entries"] } else { &["-U", "--max-entries"] };
+
+        // the hidden entry counts as well
+        let output = command(&fixture.dir, &[args, &["3"]].concat()).output().unwrap();
+        assert_eq!((output.status.code(), output.stdout.as_slice()), (Some(1), &b""[..]), "sorted: {sorted}");
+        let mut listed: Vec<String> = run(&fixture.dir, &[args, &["4"]].concat()).lines().map(String::from).collect();
+        listed.sort();
+        assert_eq!(listed, ["a", "b", "c"], "sorted: {sorted}");
+    }
+}
+
+#[test]
+fn cuts_the_window_out_of_the_sorted_listing() {
+    let fixture = Fixture::new();
+    fs::create_dir(fixture.dir.join("sub")).unwrap();
+    for name in ["a", "b", "c", "d", "e"] {
+        fixture.file(name, b"");
+    }
+    let expected: [(&[&str], &[&str]); 7] = [
+        (&["--head", "2"], &["sub", "a"]),
+        (&["--tail", "2"], &["d", "e"]),
+        (&["--offset", "2", "--head", "2"], &["b", "c"]),
+        (&["--offset", "4", "--head", "5"], &["d", "e"]),
+        (&["--offset", "1", "--head", "3", "--tail", "1"], &["c"]),
+        (&["-r", "--head", "2"], &["sub", "e"]),
+        (&["--offset", "9"], &[])
+    ];
+    for (args, names) in expected {
+        let listed = run(&fixture.dir, args);
+        assert_eq!(listed.lines().collect::<Vec<_>>(), names, "{args:?}");
+    }
+}
+
+#[test]
+fn cuts_the_window_out_of_the_listing_in_directory_order() {
+    let fixture = Fixture::new();
+    for name in ["a", "b", "c", "d", "e", "f"] {
+        fixture.file(name, b"");
+    }
+    let listed = |args: &[&str]| run(&fixture.dir, &[&["-U"], args].concat()).lines().map(String::from).collect::<Vec<_>>();
+    let all = listed(&[]);
+    assert_eq!(all.len(), 6);
+    assert_eq!(listed(&["--head", "2"]), all[..2]);
+    assert_eq!(listed(&["--offset", "2", "--head", "3"]), all[2..5]);
+    assert_eq!(listed(&["--tail", "2"]), all[4..]);
+    assert_eq!(listed(&["--offset", "5", "--head", "3"]), all[5..]);
+}
+
+#[test]
+fn cuts_the_window_out_of_what_passes_the_filters() {
+    let fixture = Fixture::new();
+    for (name, content) in [("a", &b""[..]), ("b", b"full"), ("c", b""), ("d", b""), ("e", b"full")] {
+        fixture.file(name, content);
+    }
+    assert_eq!(run(&fixture.dir, &["--only-empty", "--head", "2"]), "a\nc\n");
+    assert_eq!(run(&fixture.dir, &["--only-empty", "--offset", "1", "--tail", "1"]), "d\n");
+
+    let empty: Vec<String> = run(&fixture.dir, &["-U", "--only-empty"]).lines().map(String::from).collect();
+    let mut sorted = empty.clone();
+    sorted.sort();
+    assert_eq!(sorted, ["a", "c", "d"]);
+    assert_eq!(run(&fixture.dir, &["-U", "--only-empty", "--head", "2"]), format!("{}\n{}\n", empty[0], empty[1]));
+}